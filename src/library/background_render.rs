@@ -0,0 +1,88 @@
+use std::sync::mpsc::{self, TryRecvError};
+use std::thread;
+
+use crate::library::canvas::Canvas;
+use crate::library::tile_renderer::Tile;
+
+/// Runs a render on a background thread so whoever's displaying it stays
+/// responsive. Finished tiles are queued until `receive_tiles` copies them
+/// onto a canvas.
+pub struct BackgroundRender {
+    receiver: mpsc::Receiver<Tile>,
+    is_finished: bool,
+}
+
+impl BackgroundRender {
+    /// `render` runs on a new thread and should pass each tile to the callback
+    /// it's given as soon as the tile's ready.
+    pub fn spawn<R>(render: R) -> Self
+    where
+        R: FnOnce(&mut dyn FnMut(Tile)) + Send + 'static,
+    {
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            render(&mut |tile| {
+                // The receiver is only gone if nobody's watching anymore, in
+                // which case there's nowhere for the tile to go.
+                let _ = sender.send(tile);
+            });
+        });
+        BackgroundRender {
+            receiver,
+            is_finished: false,
+        }
+    }
+
+    /// Copies every tile that's finished since the last call onto `canvas`,
+    /// without blocking. Returns `true` if anything was copied.
+    pub fn receive_tiles(&mut self, canvas: &mut Canvas) -> bool {
+        let mut received_any = false;
+        loop {
+            match self.receiver.try_recv() {
+                Ok(tile) => {
+                    canvas.set_tile(&tile);
+                    received_any = true;
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    self.is_finished = true;
+                    break;
+                }
+            }
+        }
+        received_any
+    }
+
+    /// `true` once the render is done and every tile has been received.
+    pub fn is_finished(&self) -> bool {
+        self.is_finished
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::library::tile_renderer::TileRenderer;
+    use crate::ray_tracer_challenge::{pt2, Rgb};
+
+    #[test]
+    fn receives_every_tile_from_the_background_thread() {
+        let mut background_render = BackgroundRender::spawn(|on_tile| {
+            let shade = |_| Rgb::new(0.0, 1.0, 0.0);
+            TileRenderer::new()
+                .with_tile_size(4)
+                .render_tiles(10, 10, shade, on_tile);
+        });
+
+        let mut canvas = Canvas::new(10, 10);
+        while !background_render.is_finished() {
+            background_render.receive_tiles(&mut canvas);
+        }
+
+        for xy in canvas.iter_points() {
+            assert_eq!(canvas.rgb_at(xy), Some(Rgb::new(0.0, 1.0, 0.0)));
+        }
+        assert!(!background_render.receive_tiles(&mut canvas));
+        assert_eq!(canvas.rgb_at(pt2(9.0, 9.0)), Some(Rgb::new(0.0, 1.0, 0.0)));
+    }
+}
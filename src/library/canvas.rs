@@ -1,190 +1,160 @@
 use camino::Utf8Path;
 use image::ImageResult;
 use palette::{FromColor, Oklcha, Srgba};
-use std::time::{Duration, Instant};
 
-use crate::crate_wrappers::pixels::{Pixels, NUM_COMPONENTS_IN_COLOR};
+use crate::crate_wrappers::pixels::NUM_COMPONENTS_IN_COLOR;
+
+use crate::library::framebuffer::{Framebuffer, RgbFramebuffer, ToneMapping};
 use crate::ray_tracer_challenge::{pt2, size2, Point2, Rgb, Size2};
 
-/// `pixels` stores colors internally as four instances of `u8`.
-pub struct Canvas {
-    pub pixels: Pixels,
-    width: u32,
-    height: u32,
-    last_render_at: Instant,
-    max_time_since_last_render: Duration,
+/// A grid of pixels to draw into. Where the pixels are stored is up to the
+/// `Framebuffer`; nothing here depends on a window or a GPU.
+pub struct Canvas<F: Framebuffer = RgbFramebuffer> {
+    framebuffer: F,
 }
 
-impl Canvas {
-    pub fn new(width: u32, height: u32, pixels: Pixels) -> Self {
-        let target_fps = 60.0;
-        let max_time_since_last_render = Duration::from_secs_f64(1.0 / target_fps);
+impl<F: Framebuffer> Canvas<F> {
+    pub fn new(width: u32, height: u32) -> Self {
         Self {
-            width,
-            height,
-            pixels,
-            last_render_at: Instant::now(),
-            max_time_since_last_render,
+            framebuffer: F::new(width, height),
         }
     }
 
     pub fn set_pixel(&mut self, xy: Point2, oklcha: Oklcha) {
         let srgba = Srgba::from_color(oklcha);
-        self.update_frame_slice(xy, srgba);
+        let rgb = Rgb::new(srgba.red, srgba.green, srgba.blue);
+        self.set_pixel_rgb(xy, rgb);
     }
 
     pub fn set_pixel_rgb(&mut self, xy: Point2, rgb: Rgb) {
-        self.update_frame_slice(xy, rgb.into());
+        if let Some((x, y)) = self.pixel_coordinates(xy) {
+            self.framebuffer.set_rgb(x, y, rgb);
+        }
     }
 
     pub fn iter_points(&self) -> impl Iterator<Item = Point2> {
-        let width = self.width;
-        let height = self.height;
+        let width = self.framebuffer.width();
+        let height = self.framebuffer.height();
         (0..width).flat_map(move |x| (0..height).map(move |y| pt2(x as f32, y as f32)))
     }
 
     pub fn rgb_at(&self, xy: Point2) -> Option<Rgb> {
-        let components = self.frame_slice(xy)?;
-        let rgb = components.into();
-        Some(rgb)
+        let (x, y) = self.pixel_coordinates(xy)?;
+        self.framebuffer.rgb_at(x, y)
     }
 
+    /// Iterates row by row, starting at the top left.
     pub fn iter_pixels(&self) -> impl Iterator<Item = Pixel> + '_ {
-        self.pixels
-            .frame()
-            .chunks_exact(NUM_COMPONENTS_IN_COLOR)
-            .enumerate()
-            .map(|(index, chunk)| Pixel {
-                xy: self.xy_from_frame_index(index),
-                rgb: chunk.into(),
+        let width = self.framebuffer.width();
+        let height = self.framebuffer.height();
+        (0..height).flat_map(move |y| {
+            (0..width).map(move |x| Pixel {
+                xy: pt2(x as f32, y as f32),
+                rgb: self.framebuffer.rgb_at(x, y).unwrap_or_else(Rgb::black),
             })
+        })
     }
 
-    /// Assumes the default texture format: `wgpu::TextureFormat::Rgba8UnormSrgb`.
-    /// This maps to Srgba<u8> in the palette crate.
-    fn update_frame_slice(&mut self, xy: Point2, srgba: Srgba<f32>) {
-        let srgba_u8: Srgba<u8> = srgba.into_format();
-        let srgba_components: [u8; NUM_COMPONENTS_IN_COLOR] = palette::cast::into_array(srgba_u8);
-        if let Some(frame_slice) = self.frame_slice_mut(xy) {
-            frame_slice.copy_from_slice(&srgba_components);
-            self.render_if_needed();
-        }
+    /// Copies the canvas into a frame laid out like `pixels`' default texture
+    /// format, `wgpu::TextureFormat::Rgba8UnormSrgb`.
+    pub fn write_frame(&self, frame: &mut [u8]) {
+        self.framebuffer.write_srgba8(frame);
     }
 
-    fn frame_slice(&self, xy: Point2) -> Option<&[u8]> {
-        let start = self.frame_index(xy);
-        let end = start + NUM_COMPONENTS_IN_COLOR;
-        let frame = self.pixels.frame();
-
-        if end > frame.len() {
-            return None;
-        }
-
-        let slice = &frame[start..end];
-        Some(slice)
+    /// Resizes the canvas and clears it to black.
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.framebuffer.resize(width, height);
     }
 
-    fn frame_slice_mut(&mut self, xy: Point2) -> Option<&mut [u8]> {
-        let start = self.frame_index(xy);
-        let end = start + NUM_COMPONENTS_IN_COLOR;
-        let frame = self.pixels.frame_mut();
-
-        if end > frame.len() {
+    /// Rounds `xy` to the nearest pixel, returning `None` if it's off the canvas.
+    fn pixel_coordinates(&self, xy: Point2) -> Option<(u32, u32)> {
+        let x = xy.x().round();
+        let y = xy.y().round();
+        if x < 0.0 || y < 0.0 || x >= self.width() || y >= self.height() {
             return None;
         }
-
-        let slice = &mut frame[start..end];
-        Some(slice)
+        Some((x as u32, y as u32))
     }
 
-    fn render_if_needed(&mut self) {
-        let now = Instant::now();
-        let time_since_last_render = now - self.last_render_at;
-
-        if time_since_last_render > self.max_time_since_last_render {
-            self.pixels.render().expect("pixels.render() failed.");
-            self.last_render_at = Instant::now();
+    /// Loads any image format the `image` crate supports. Components are scaled
+    /// to 0.0 to 1.0, except for HDR formats, which keep their full range.
+    pub fn load_image(path: &Utf8Path) -> ImageResult<Self> {
+        let image = image::open(path)?.into_rgb32f();
+        let mut canvas = Canvas::new(image.width(), image.height());
+        for (x, y, pixel) in image.enumerate_pixels() {
+            let [red, green, blue] = pixel.0;
+            canvas.set_pixel_rgb(pt2(x as f32, y as f32), Rgb::new(red, green, blue));
         }
+        Ok(canvas)
     }
 
-    /// Given an xy coordinate, return the index of the first component of the color at that pixel.
-    fn frame_index(&self, xy: Point2) -> usize {
-        let x_usize = xy.x().round() as usize;
-        let y_usize = xy.y().round() as usize;
-        let width_usize = self.width as usize;
-
-        // Incorporate NUM_COMPONENTS_IN_COLOR to account for the fact that each pixel has multiple components.
-        (y_usize * width_usize + x_usize) * NUM_COMPONENTS_IN_COLOR
-    }
-
-    fn xy_from_frame_index(&self, index: usize) -> Point2 {
-        let width_usize = self.width as usize;
-        let x = index % width_usize;
-        let y = index / width_usize;
-        pt2(x as f32, y as f32)
-    }
-
+    /// Quantizes the canvas to 8 bits per component and saves it to `path`.
     pub fn save_image(&self, path: &Utf8Path) -> ImageResult<()> {
-        let mut image_buffer = image::ImageBuffer::new(self.width, self.height);
-        for pixel in self.iter_pixels() {
-            let x = pixel.xy.x().round() as u32;
-            let y = pixel.xy.y().round() as u32;
-            let image_buffer_pixel = image_buffer.get_pixel_mut(x, y);
-
-            let srgba_f32: Srgba<f32> = pixel.rgb.into();
-            let srgba_u8: Srgba<u8> = srgba_f32.into_format();
-            let r = srgba_u8.red;
-            let g = srgba_u8.green;
-            let b = srgba_u8.blue;
-
-            *image_buffer_pixel = image::Rgba([r, g, b, 255]);
-        }
+        let width = self.framebuffer.width();
+        let height = self.framebuffer.height();
+        let mut frame = vec![0; width as usize * height as usize * NUM_COMPONENTS_IN_COLOR];
+        self.write_frame(&mut frame);
+        let image_buffer: image::RgbaImage = image::ImageBuffer::from_raw(width, height, frame)
+            .expect("Frame should match the canvas size.");
         image_buffer.save(path)
     }
 
     pub fn width(&self) -> f32 {
-        self.width as f32
+        self.framebuffer.width() as f32
     }
 
     pub fn height(&self) -> f32 {
-        self.height as f32
+        self.framebuffer.height() as f32
     }
 
     pub fn size(&self) -> Size2 {
-        size2(self.width as f32, self.height as f32)
+        size2(self.width(), self.height())
+    }
+}
+
+impl Canvas<RgbFramebuffer> {
+    /// Adds another sample to the pixel at `xy`. Reading the pixel returns the
+    /// average of its samples.
+    pub fn add_sample(&mut self, xy: Point2, rgb: Rgb) {
+        if let Some((x, y)) = self.pixel_coordinates(xy) {
+            self.framebuffer.add_sample(x, y, rgb);
+        }
+    }
+
+    pub fn set_tone_mapping(&mut self, tone_mapping: ToneMapping) {
+        self.framebuffer.set_tone_mapping(tone_mapping);
     }
 }
 
 pub struct Pixel {
-    xy: Point2,
-    rgb: Rgb,
+    pub xy: Point2,
+    pub rgb: Rgb,
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::library::image_comparison::{assert_matches_golden_image, Tolerance};
     use crate::ray_tracer_challenge::pt2;
 
     #[test]
     fn colors_default_to_black() {
         let width = 10;
         let height = 20;
-        let pixels = Pixels::new(width, height);
-        let canvas = Canvas::new(width, height, pixels);
+        let canvas: Canvas = Canvas::new(width, height);
         let black = Rgb::black();
         for pixel in canvas.iter_pixels() {
             assert_eq!(pixel.rgb, black);
         }
-        assert_eq!(canvas.pixels.width, 10);
-        assert_eq!(canvas.pixels.height, 20);
+        assert_eq!(canvas.width(), 10.0);
+        assert_eq!(canvas.height(), 20.0);
     }
 
     #[test]
     fn set_pixel_rgb() {
         let width = 10;
         let height = 20;
-        let pixels = Pixels::new(width, height);
-        let mut canvas = Canvas::new(width, height, pixels);
+        let mut canvas: Canvas = Canvas::new(width, height);
 
         let red = Rgb::new(1.0, 0.0, 0.0);
         let xy = pt2(2.0, 3.0);
@@ -194,12 +164,33 @@ mod tests {
         assert_eq!(rgb, red);
     }
 
+    #[test]
+    fn rgb_at_round_trips_hdr_colors() {
+        let mut canvas: Canvas = Canvas::new(10, 20);
+
+        let bright = Rgb::new(4.0, 0.123, 1.01);
+        let xy = pt2(2.0, 3.0);
+        canvas.set_pixel_rgb(xy, bright);
+
+        assert_eq!(canvas.rgb_at(xy), Some(bright));
+    }
+
+    #[test]
+    fn add_sample_averages_samples() {
+        let mut canvas: Canvas = Canvas::new(10, 20);
+
+        let xy = pt2(2.0, 3.0);
+        canvas.add_sample(xy, Rgb::new(1.0, 0.0, 0.0));
+        canvas.add_sample(xy, Rgb::new(0.0, 0.0, 1.0));
+
+        assert_eq!(canvas.rgb_at(xy), Some(Rgb::new(0.5, 0.0, 0.5)));
+    }
+
     #[test]
     fn save_image() {
         let width = 10;
         let height = 20;
-        let pixels = Pixels::new(width, height);
-        let mut canvas = Canvas::new(width, height, pixels);
+        let mut canvas: Canvas = Canvas::new(width, height);
 
         let red = Rgb::new(1.0, 0.0, 0.0);
         let xy = pt2(2.0, 3.0);
@@ -208,9 +199,20 @@ mod tests {
         let path = Utf8Path::new("src/tests/save_image_actual_output.webp");
         canvas.save_image(path).expect("Failed to save image.");
 
-        let actual_bytes = std::fs::read(path).expect("Failed to read file.");
-        let expected_bytes = include_bytes!("../tests/save_image_expected_output.webp");
-        assert_eq!(actual_bytes, expected_bytes);
+        let saved: Canvas = Canvas::load_image(path).expect("Failed to load image.");
+        let expected_path = Utf8Path::new("src/tests/save_image_expected_output.webp");
+        assert_matches_golden_image(&saved, expected_path, Tolerance::exact());
         // std::fs::remove_file(path).expect("Failed to remove file.");
     }
+
+    #[test]
+    fn load_image() {
+        let path = Utf8Path::new("src/tests/save_image_expected_output.webp");
+        let canvas: Canvas = Canvas::load_image(path).expect("Failed to load image.");
+
+        assert_eq!(canvas.width(), 10.0);
+        assert_eq!(canvas.height(), 20.0);
+        assert_eq!(canvas.rgb_at(pt2(2.0, 3.0)), Some(Rgb::new(1.0, 0.0, 0.0)));
+        assert_eq!(canvas.rgb_at(pt2(3.0, 3.0)), Some(Rgb::black()));
+    }
 }
@@ -0,0 +1,219 @@
+use palette::Srgba;
+
+use crate::crate_wrappers::pixels::NUM_COMPONENTS_IN_COLOR;
+use crate::ray_tracer_challenge::Rgb;
+
+/// Where a `Canvas` keeps its pixels. Framebuffers live in ordinary memory so
+/// that drawing, saving and testing never need a GPU; a window copies the
+/// finished frame into its surface with `write_srgba8`.
+pub trait Framebuffer {
+    fn new(width: u32, height: u32) -> Self;
+    fn width(&self) -> u32;
+    fn height(&self) -> u32;
+    /// Returns `None` if `(x, y)` is outside the framebuffer.
+    fn rgb_at(&self, x: u32, y: u32) -> Option<Rgb>;
+    /// Does nothing if `(x, y)` is outside the framebuffer.
+    fn set_rgb(&mut self, x: u32, y: u32, rgb: Rgb);
+    /// Writes every pixel into `frame` as `Srgba<u8>` components, which is the
+    /// layout `pixels` uses for its default `Rgba8UnormSrgb` texture format.
+    fn write_srgba8(&self, frame: &mut [u8]);
+    /// Resizes the framebuffer and clears it to black.
+    fn resize(&mut self, width: u32, height: u32);
+}
+
+/// Stores each pixel as an `f32` `Rgb`, so values above 1.0 survive until the
+/// frame is presented or saved. Each pixel can accumulate several samples,
+/// which are averaged when read.
+pub struct RgbFramebuffer {
+    width: u32,
+    height: u32,
+    sums: Vec<Rgb>,
+    sample_counts: Vec<u32>,
+    tone_mapping: ToneMapping,
+}
+
+impl RgbFramebuffer {
+    pub fn add_sample(&mut self, x: u32, y: u32, rgb: Rgb) {
+        let Some(index) = self.pixel_index(x, y) else {
+            return;
+        };
+        self.sums[index] = self.sums[index] + rgb;
+        self.sample_counts[index] += 1;
+    }
+
+    pub fn sample_count(&self, x: u32, y: u32) -> Option<u32> {
+        let index = self.pixel_index(x, y)?;
+        Some(self.sample_counts[index])
+    }
+
+    pub fn set_tone_mapping(&mut self, tone_mapping: ToneMapping) {
+        self.tone_mapping = tone_mapping;
+    }
+
+    fn pixel_index(&self, x: u32, y: u32) -> Option<usize> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        Some(y as usize * self.width as usize + x as usize)
+    }
+
+    fn average(&self, index: usize) -> Rgb {
+        match self.sample_counts[index] {
+            0 => Rgb::black(),
+            count => self.sums[index] * (1.0 / count as f32),
+        }
+    }
+}
+
+impl Framebuffer for RgbFramebuffer {
+    fn new(width: u32, height: u32) -> Self {
+        let num_pixels = width as usize * height as usize;
+        RgbFramebuffer {
+            width,
+            height,
+            sums: vec![Rgb::black(); num_pixels],
+            sample_counts: vec![0; num_pixels],
+            tone_mapping: ToneMapping::default(),
+        }
+    }
+
+    fn width(&self) -> u32 {
+        self.width
+    }
+
+    fn height(&self) -> u32 {
+        self.height
+    }
+
+    fn rgb_at(&self, x: u32, y: u32) -> Option<Rgb> {
+        let index = self.pixel_index(x, y)?;
+        Some(self.average(index))
+    }
+
+    fn set_rgb(&mut self, x: u32, y: u32, rgb: Rgb) {
+        let Some(index) = self.pixel_index(x, y) else {
+            return;
+        };
+        self.sums[index] = rgb;
+        self.sample_counts[index] = 1;
+    }
+
+    fn write_srgba8(&self, frame: &mut [u8]) {
+        assert_eq!(
+            frame.len(),
+            self.sums.len() * NUM_COMPONENTS_IN_COLOR,
+            "frame must hold exactly one Srgba<u8> per pixel"
+        );
+        let chunks = frame.chunks_exact_mut(NUM_COMPONENTS_IN_COLOR);
+        for (index, chunk) in chunks.enumerate() {
+            let rgb = self.tone_mapping.apply(self.average(index));
+            chunk.copy_from_slice(&quantize(rgb));
+        }
+    }
+
+    fn resize(&mut self, width: u32, height: u32) {
+        let tone_mapping = self.tone_mapping;
+        *self = RgbFramebuffer::new(width, height);
+        self.tone_mapping = tone_mapping;
+    }
+}
+
+/// How HDR colors are squeezed into the 0.0 to 1.0 range before quantizing.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum ToneMapping {
+    /// Anything outside 0.0 to 1.0 is clipped.
+    #[default]
+    Clamp,
+    /// Maps each component `c` to `c / (1 + c)`, after scaling by `exposure`.
+    Reinhard { exposure: f32 },
+}
+
+impl ToneMapping {
+    pub fn apply(&self, rgb: Rgb) -> Rgb {
+        match self {
+            ToneMapping::Clamp => rgb,
+            ToneMapping::Reinhard { exposure } => {
+                let [red, green, blue] = (rgb * *exposure).components().map(|c| c / (1.0 + c));
+                Rgb::new(red, green, blue)
+            }
+        }
+    }
+}
+
+/// Converts to `Srgba<u8>` components, clamping anything outside 0.0 to 1.0.
+fn quantize(rgb: Rgb) -> [u8; NUM_COMPONENTS_IN_COLOR] {
+    let srgba: Srgba<f32> = rgb.into();
+    let srgba_u8: Srgba<u8> = srgba.into_format();
+    palette::cast::into_array(srgba_u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writes_components_in_srgba8_order() {
+        let mut framebuffer = RgbFramebuffer::new(2, 1);
+        framebuffer.set_rgb(1, 0, Rgb::new(1.0, 0.0, 1.0));
+
+        let mut frame = vec![0; 2 * NUM_COMPONENTS_IN_COLOR];
+        framebuffer.write_srgba8(&mut frame);
+        assert_eq!(frame, vec![0, 0, 0, 255, 255, 0, 255, 255]);
+    }
+
+    #[test]
+    fn keeps_colors_brighter_than_white() {
+        let mut framebuffer = RgbFramebuffer::new(1, 1);
+        let bright = Rgb::new(1.5, 0.25, 3.0);
+        framebuffer.set_rgb(0, 0, bright);
+        assert_eq!(framebuffer.rgb_at(0, 0), Some(bright));
+    }
+
+    #[test]
+    fn averages_accumulated_samples() {
+        let mut framebuffer = RgbFramebuffer::new(1, 1);
+        framebuffer.add_sample(0, 0, Rgb::new(1.0, 0.0, 2.0));
+        framebuffer.add_sample(0, 0, Rgb::new(0.0, 0.0, 1.0));
+        assert_eq!(framebuffer.rgb_at(0, 0), Some(Rgb::new(0.5, 0.0, 1.5)));
+        assert_eq!(framebuffer.sample_count(0, 0), Some(2));
+    }
+
+    #[test]
+    fn set_rgb_discards_accumulated_samples() {
+        let mut framebuffer = RgbFramebuffer::new(1, 1);
+        framebuffer.add_sample(0, 0, Rgb::new(1.0, 1.0, 1.0));
+        framebuffer.add_sample(0, 0, Rgb::new(1.0, 1.0, 1.0));
+        framebuffer.set_rgb(0, 0, Rgb::new(0.2, 0.4, 0.6));
+        assert_eq!(framebuffer.rgb_at(0, 0), Some(Rgb::new(0.2, 0.4, 0.6)));
+        assert_eq!(framebuffer.sample_count(0, 0), Some(1));
+    }
+
+    #[test]
+    fn quantizes_only_when_writing_the_frame() {
+        let mut framebuffer = RgbFramebuffer::new(2, 1);
+        framebuffer.set_rgb(0, 0, Rgb::new(2.0, 0.5, -1.0));
+        framebuffer.set_tone_mapping(ToneMapping::Reinhard { exposure: 1.0 });
+        framebuffer.set_rgb(1, 0, Rgb::new(1.0, 0.0, 3.0));
+
+        let mut frame = vec![0; 2 * NUM_COMPONENTS_IN_COLOR];
+        framebuffer.write_srgba8(&mut frame);
+        // Reinhard maps 2.0 to 0.667, 0.5 to 0.333, 1.0 to 0.5 and 3.0 to 0.75.
+        assert_eq!(frame, vec![170, 85, 0, 255, 128, 0, 191, 255]);
+    }
+
+    #[test]
+    #[should_panic(expected = "one Srgba<u8> per pixel")]
+    fn refuses_to_write_a_frame_of_the_wrong_size() {
+        let framebuffer = RgbFramebuffer::new(2, 1);
+        let mut frame = vec![0; 3 * NUM_COMPONENTS_IN_COLOR];
+        framebuffer.write_srgba8(&mut frame);
+    }
+
+    #[test]
+    fn ignores_pixels_outside_the_framebuffer() {
+        let mut framebuffer = RgbFramebuffer::new(2, 2);
+        framebuffer.set_rgb(2, 0, Rgb::new(1.0, 1.0, 1.0));
+        assert_eq!(framebuffer.rgb_at(2, 0), None);
+        assert_eq!(framebuffer.rgb_at(0, 1), Some(Rgb::black()));
+    }
+}
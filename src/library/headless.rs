@@ -0,0 +1,40 @@
+use camino::Utf8Path;
+use image::ImageResult;
+
+use crate::library::canvas::Canvas;
+use crate::Sketch;
+
+/// Draws the sketch into an in-memory canvas and saves it to `path`,
+/// without opening a window. Useful on machines with no display.
+pub fn render_to_file(
+    sketch: &Sketch,
+    path: &Utf8Path,
+    width: u32,
+    height: u32,
+) -> ImageResult<()> {
+    render(sketch, width, height).save_image(path)
+}
+
+/// Draws the sketch into a new in-memory canvas.
+pub fn render(sketch: &Sketch, width: u32, height: u32) -> Canvas {
+    let mut canvas = Canvas::new(width, height);
+    sketch.draw(&mut canvas);
+    canvas
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::library::image_comparison::{assert_matches_golden_image, Tolerance};
+    use crate::{CANVAS_HEIGHT, CANVAS_WIDTH};
+
+    #[test]
+    fn sketch_matches_golden_image() {
+        let width = CANVAS_WIDTH.round() as u32;
+        let height = CANVAS_HEIGHT.round() as u32;
+        let canvas = render(&Sketch::new(), width, height);
+
+        let expected_path = Utf8Path::new("src/tests/sketch_expected_output.png");
+        assert_matches_golden_image(&canvas, expected_path, Tolerance::perceptual());
+    }
+}
@@ -0,0 +1,266 @@
+use camino::{Utf8Path, Utf8PathBuf};
+
+use std::f32::consts::PI;
+
+use crate::library::canvas::Canvas;
+use crate::ray_tracer_challenge::{pt2, vec3, Camera, Point3, Ray, Rgb, Transform};
+
+/// How the difference between two colors is measured.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ColorDifference {
+    /// The largest absolute difference between any red, green or blue
+    /// component, after clamping both colors to 0.0 to 1.0.
+    Rgb,
+    /// Euclidean distance in Oklab, after clamping both colors to 0.0 to 1.0.
+    /// Tracks perceived difference. Roughly 0.02 is where most people start to
+    /// notice.
+    OklabDeltaE,
+}
+
+impl ColorDifference {
+    pub fn between(&self, a: Rgb, b: Rgb) -> f32 {
+        // Golden images are saved as 8 bits per channel, which clamps any HDR
+        // colors, so compare the colors as they'd be saved.
+        let a = a.clamp();
+        let b = b.clamp();
+        match self {
+            ColorDifference::Rgb => {
+                let differences = (a - b).components().map(f32::abs);
+                differences.into_iter().fold(0.0, f32::max)
+            }
+            ColorDifference::OklabDeltaE => a.oklab_distance(b),
+        }
+    }
+}
+
+/// How different two images may be before they're considered a mismatch.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tolerance {
+    pub color_difference: ColorDifference,
+    /// No single pixel may differ by more than this.
+    pub max_error: f32,
+    /// The average difference across all pixels may not exceed this.
+    pub mean_error: f32,
+}
+
+impl Tolerance {
+    /// Only allows differences too small to survive quantizing to 8 bits.
+    pub fn exact() -> Self {
+        Tolerance {
+            color_difference: ColorDifference::Rgb,
+            max_error: 0.5 / 255.0,
+            mean_error: 0.5 / 255.0,
+        }
+    }
+
+    /// Allows small perceptual differences, like the ones caused by tweaking
+    /// floating point math or changing image encoders.
+    pub fn perceptual() -> Self {
+        Tolerance {
+            color_difference: ColorDifference::OklabDeltaE,
+            max_error: 0.02,
+            mean_error: 0.002,
+        }
+    }
+}
+
+/// Per-pixel differences between an actual and an expected image.
+pub struct ImageComparison {
+    pub max_error: f32,
+    pub mean_error: f32,
+    pub num_differing_pixels: usize,
+    /// The expected image, darkened, with differing pixels highlighted in red.
+    /// The brightest red marks the pixel with the largest error.
+    pub diff: Canvas,
+}
+
+impl ImageComparison {
+    /// Returns `None` if the images aren't the same size.
+    pub fn new(
+        actual: &Canvas,
+        expected: &Canvas,
+        color_difference: ColorDifference,
+    ) -> Option<Self> {
+        if actual.width() != expected.width() || actual.height() != expected.height() {
+            return None;
+        }
+
+        let errors: Vec<(Rgb, f32)> = expected
+            .iter_pixels()
+            .map(|pixel| {
+                let actual_rgb = actual.rgb_at(pixel.xy).unwrap_or_else(Rgb::black);
+                let error = color_difference.between(actual_rgb, pixel.rgb);
+                (pixel.rgb, error)
+            })
+            .collect();
+
+        let max_error = errors.iter().map(|(_, error)| *error).fold(0.0, f32::max);
+        let total_error: f32 = errors.iter().map(|(_, error)| error).sum();
+        let mean_error = total_error / errors.len().max(1) as f32;
+        let num_differing_pixels = errors.iter().filter(|(_, error)| *error > 0.0).count();
+
+        let width = actual.width() as u32;
+        let height = actual.height() as u32;
+        let mut diff = Canvas::new(width, height);
+        for (index, (expected_rgb, error)) in errors.into_iter().enumerate() {
+            let xy = pt2((index as u32 % width) as f32, (index as u32 / width) as f32);
+            let highlight = if max_error > 0.0 {
+                error / max_error
+            } else {
+                0.0
+            };
+            diff.set_pixel_rgb(xy, expected_rgb * 0.25 + Rgb::new(highlight, 0.0, 0.0));
+        }
+
+        Some(ImageComparison {
+            max_error,
+            mean_error,
+            num_differing_pixels,
+            diff,
+        })
+    }
+
+    pub fn is_within(&self, tolerance: Tolerance) -> bool {
+        self.max_error <= tolerance.max_error && self.mean_error <= tolerance.mean_error
+    }
+}
+
+/// Panics unless `actual` matches the image at `expected_path` within
+/// `tolerance`. On a mismatch, the actual image and a diff image are saved
+/// next to the expected one, as `<name>_actual.png` and `<name>_diff.png`.
+///
+/// Set `UPDATE_GOLDEN_IMAGES=1` to overwrite the expected image instead.
+pub fn assert_matches_golden_image(
+    actual: &Canvas,
+    expected_path: &Utf8Path,
+    tolerance: Tolerance,
+) {
+    if std::env::var("UPDATE_GOLDEN_IMAGES").is_ok_and(|value| value == "1") {
+        actual
+            .save_image(expected_path)
+            .expect("Failed to update golden image.");
+        return;
+    }
+
+    let expected: Canvas = Canvas::load_image(expected_path)
+        .unwrap_or_else(|err| panic!("Failed to load golden image {expected_path}: {err}"));
+    let actual_path = sibling_path(expected_path, "actual");
+    let diff_path = sibling_path(expected_path, "diff");
+
+    let Some(comparison) = ImageComparison::new(actual, &expected, tolerance.color_difference)
+    else {
+        actual
+            .save_image(&actual_path)
+            .expect("Failed to save actual image.");
+        panic!(
+            "Image is {}x{}, but {expected_path} is {}x{}. Saved the actual image to {actual_path}.",
+            actual.width(),
+            actual.height(),
+            expected.width(),
+            expected.height(),
+        );
+    };
+
+    if !comparison.is_within(tolerance) {
+        actual
+            .save_image(&actual_path)
+            .expect("Failed to save actual image.");
+        comparison
+            .diff
+            .save_image(&diff_path)
+            .expect("Failed to save diff image.");
+        panic!(
+            "Image doesn't match {expected_path}.\n  \
+             {} pixels differ, max error {} (tolerance {}), mean error {} (tolerance {}), \
+             measured with {:?}.\n  \
+             Saved the actual image to {actual_path} and a diff to {diff_path}.",
+            comparison.num_differing_pixels,
+            comparison.max_error,
+            tolerance.max_error,
+            comparison.mean_error,
+            tolerance.mean_error,
+            tolerance.color_difference,
+        );
+    }
+}
+
+/// Renders a small 48x32 view of a scene, looking from `from` to `to`, and
+/// checks it with `assert_matches_golden_image` at `Tolerance::perceptual`.
+pub fn assert_scene_matches_golden_image(
+    color_for_ray: impl Fn(Ray) -> Rgb + Sync,
+    from: Point3,
+    to: Point3,
+    expected_path: &Utf8Path,
+) {
+    let mut camera = Camera::new(48, 32, PI / 3.0);
+    camera.set_transform(Transform::view_transform(from, to, vec3(0.0, 1.0, 0.0)));
+    let canvas = camera.render(color_for_ray);
+    assert_matches_golden_image(&canvas, expected_path, Tolerance::perceptual());
+}
+
+/// `foo/expected.png` with suffix `diff` becomes `foo/expected_diff.png`.
+fn sibling_path(path: &Utf8Path, suffix: &str) -> Utf8PathBuf {
+    let stem = path.file_stem().unwrap_or("image");
+    path.with_file_name(format!("{stem}_{suffix}.png"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn canvas_with_one_pixel(rgb: Rgb) -> Canvas {
+        let mut canvas = Canvas::new(2, 2);
+        canvas.set_pixel_rgb(pt2(1.0, 1.0), rgb);
+        canvas
+    }
+
+    #[test]
+    fn identical_images_have_no_error() {
+        let a = canvas_with_one_pixel(Rgb::new(0.2, 0.4, 0.6));
+        let b = canvas_with_one_pixel(Rgb::new(0.2, 0.4, 0.6));
+        let comparison = ImageComparison::new(&a, &b, ColorDifference::Rgb).unwrap();
+        assert_eq!(comparison.max_error, 0.0);
+        assert_eq!(comparison.mean_error, 0.0);
+        assert_eq!(comparison.num_differing_pixels, 0);
+        assert!(comparison.is_within(Tolerance::exact()));
+    }
+
+    #[test]
+    fn measures_max_and_mean_error() {
+        let a = canvas_with_one_pixel(Rgb::new(0.2, 0.4, 0.6));
+        let b = canvas_with_one_pixel(Rgb::new(0.2, 0.8, 0.6));
+        let comparison = ImageComparison::new(&a, &b, ColorDifference::Rgb).unwrap();
+        assert!((comparison.max_error - 0.4).abs() < 0.0001);
+        assert!((comparison.mean_error - 0.1).abs() < 0.0001);
+        assert_eq!(comparison.num_differing_pixels, 1);
+        assert!(!comparison.is_within(Tolerance::exact()));
+    }
+
+    #[test]
+    fn diff_highlights_differing_pixels() {
+        let a = canvas_with_one_pixel(Rgb::new(0.0, 0.0, 0.0));
+        let b = canvas_with_one_pixel(Rgb::new(0.0, 0.0, 1.0));
+        let comparison = ImageComparison::new(&a, &b, ColorDifference::Rgb).unwrap();
+        assert_eq!(
+            comparison.diff.rgb_at(pt2(1.0, 1.0)),
+            Some(Rgb::new(1.0, 0.0, 0.25))
+        );
+        assert_eq!(comparison.diff.rgb_at(pt2(0.0, 0.0)), Some(Rgb::black()));
+    }
+
+    #[test]
+    fn images_of_different_sizes_cant_be_compared() {
+        let a: Canvas = Canvas::new(2, 2);
+        let b: Canvas = Canvas::new(2, 3);
+        assert!(ImageComparison::new(&a, &b, ColorDifference::Rgb).is_none());
+    }
+
+    #[test]
+    fn oklab_delta_e_ignores_differences_people_cant_see() {
+        let a = canvas_with_one_pixel(Rgb::new(0.5, 0.5, 0.5));
+        let b = canvas_with_one_pixel(Rgb::new(0.502, 0.5, 0.5));
+        let comparison = ImageComparison::new(&a, &b, ColorDifference::OklabDeltaE).unwrap();
+        assert!(comparison.max_error > 0.0);
+        assert!(comparison.is_within(Tolerance::perceptual()));
+    }
+}
@@ -1,3 +1,9 @@
+pub mod background_render;
 pub mod canvas;
 pub mod color;
+pub mod framebuffer;
+pub mod headless;
+pub mod image_comparison;
+pub mod ppm;
+pub mod tile_renderer;
 pub mod window;
@@ -0,0 +1,443 @@
+use crate::crate_wrappers::pixels::NUM_COMPONENTS_IN_COLOR;
+use crate::library::canvas::Canvas;
+use crate::library::framebuffer::Framebuffer;
+use crate::ray_tracer_challenge::{pt2, Rgb};
+
+/// PPM readers aren't required to handle lines longer than this.
+const MAX_PPM_LINE_LENGTH: usize = 70;
+
+impl<F: Framebuffer> Canvas<F> {
+    /// Decodes a plain-text (P3) or binary (P6) PPM. Comments are skipped and
+    /// components are scaled by the file's max value, so a component equal to
+    /// the max value becomes 1.0.
+    pub fn from_ppm(ppm: &[u8]) -> Result<Self, PpmError> {
+        let mut reader = PpmReader { ppm, position: 0 };
+        let magic_number = reader.next_token()?;
+        if magic_number != b"P3" && magic_number != b"P6" {
+            let magic_number = String::from_utf8_lossy(magic_number).into_owned();
+            return Err(PpmError::UnsupportedMagicNumber(magic_number));
+        }
+        let width = reader.next_number()?;
+        let height = reader.next_number()?;
+        let max_value = reader.next_number()?;
+        if max_value == 0 || max_value > u16::MAX as u32 {
+            return Err(PpmError::InvalidMaxValue(max_value));
+        }
+
+        if magic_number == b"P6" {
+            // Exactly one whitespace character separates the header from the data.
+            reader.position += 1;
+        }
+
+        // Check the header against the data actually present before allocating,
+        // so a corrupt size can't request an enormous canvas.
+        let min_bytes_per_component = if magic_number == b"P6" && max_value >= 256 {
+            2
+        } else {
+            1
+        };
+        let min_data_length = (width as usize)
+            .checked_mul(height as usize)
+            .and_then(|num_pixels| num_pixels.checked_mul(3 * min_bytes_per_component));
+        let remaining = reader.ppm.len().saturating_sub(reader.position);
+        match min_data_length {
+            Some(length) if length <= remaining => {}
+            _ => return Err(PpmError::SizeExceedsData { width, height }),
+        }
+
+        let mut next_component = || -> Result<f32, PpmError> {
+            let component = if magic_number == b"P3" {
+                reader.next_number()?
+            } else {
+                reader.next_binary_component(max_value)?
+            };
+            if component > max_value {
+                return Err(PpmError::ComponentTooLarge(component));
+            }
+            Ok(component as f32 / max_value as f32)
+        };
+
+        let mut canvas = Canvas::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                let red = next_component()?;
+                let green = next_component()?;
+                let blue = next_component()?;
+                canvas.set_pixel_rgb(pt2(x as f32, y as f32), Rgb::new(red, green, blue));
+            }
+        }
+        Ok(canvas)
+    }
+
+    /// Encodes the canvas as a plain-text (P3) PPM. Each row of pixels starts on
+    /// a new line, and lines are wrapped so none are longer than 70 characters.
+    pub fn to_ppm(&self) -> String {
+        let mut ppm = self.ppm_header("P3");
+        let row_length = self.width() as usize * 3;
+        for row in self.rgb_components().chunks(row_length.max(1)) {
+            let mut line = String::new();
+            for component in row {
+                let component = component.to_string();
+                if !line.is_empty() && line.len() + 1 + component.len() > MAX_PPM_LINE_LENGTH {
+                    ppm.push_str(&line);
+                    ppm.push('\n');
+                    line.clear();
+                }
+                if !line.is_empty() {
+                    line.push(' ');
+                }
+                line.push_str(&component);
+            }
+            ppm.push_str(&line);
+            ppm.push('\n');
+        }
+        ppm
+    }
+
+    /// Encodes the canvas as a binary (P6) PPM.
+    pub fn to_ppm_p6(&self) -> Vec<u8> {
+        let mut ppm = self.ppm_header("P6").into_bytes();
+        ppm.extend(self.rgb_components());
+        ppm
+    }
+
+    fn ppm_header(&self, magic_number: &str) -> String {
+        let width = self.width() as u32;
+        let height = self.height() as u32;
+        format!("{magic_number}\n{width} {height}\n255\n")
+    }
+
+    /// Quantizes the canvas the same way presenting it does, then drops alpha.
+    fn rgb_components(&self) -> Vec<u8> {
+        let num_pixels = self.width() as usize * self.height() as usize;
+        let mut frame = vec![0; num_pixels * NUM_COMPONENTS_IN_COLOR];
+        self.write_frame(&mut frame);
+        frame
+            .chunks_exact(NUM_COMPONENTS_IN_COLOR)
+            .flat_map(|srgba| &srgba[..3])
+            .copied()
+            .collect()
+    }
+}
+
+struct PpmReader<'a> {
+    ppm: &'a [u8],
+    position: usize,
+}
+
+impl<'a> PpmReader<'a> {
+    /// Returns the next whitespace-separated token, skipping `#` comments.
+    fn next_token(&mut self) -> Result<&'a [u8], PpmError> {
+        loop {
+            match self.ppm.get(self.position) {
+                None => return Err(PpmError::UnexpectedEnd),
+                Some(b'#') => {
+                    while self
+                        .ppm
+                        .get(self.position)
+                        .is_some_and(|&byte| byte != b'\n')
+                    {
+                        self.position += 1;
+                    }
+                }
+                Some(byte) if byte.is_ascii_whitespace() => self.position += 1,
+                Some(_) => break,
+            }
+        }
+        let start = self.position;
+        while self
+            .ppm
+            .get(self.position)
+            .is_some_and(|byte| !byte.is_ascii_whitespace() && *byte != b'#')
+        {
+            self.position += 1;
+        }
+        Ok(&self.ppm[start..self.position])
+    }
+
+    fn next_number(&mut self) -> Result<u32, PpmError> {
+        let token = self.next_token()?;
+        std::str::from_utf8(token)
+            .ok()
+            .and_then(|token| token.parse().ok())
+            .ok_or_else(|| PpmError::InvalidNumber(String::from_utf8_lossy(token).into_owned()))
+    }
+
+    /// Binary components are one byte each, or two big-endian bytes if the max
+    /// value doesn't fit in a byte.
+    fn next_binary_component(&mut self, max_value: u32) -> Result<u32, PpmError> {
+        let num_bytes = if max_value < 256 { 1 } else { 2 };
+        let end = self.position + num_bytes;
+        let bytes = self
+            .ppm
+            .get(self.position..end)
+            .ok_or(PpmError::UnexpectedEnd)?;
+        self.position = end;
+        Ok(bytes
+            .iter()
+            .fold(0, |component, &byte| component << 8 | byte as u32))
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum PpmError {
+    UnsupportedMagicNumber(String),
+    InvalidNumber(String),
+    InvalidMaxValue(u32),
+    ComponentTooLarge(u32),
+    SizeExceedsData { width: u32, height: u32 },
+    UnexpectedEnd,
+}
+
+impl std::fmt::Display for PpmError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PpmError::UnsupportedMagicNumber(magic_number) => {
+                write!(f, "unsupported PPM magic number {magic_number:?}")
+            }
+            PpmError::InvalidNumber(token) => write!(f, "expected a number, found {token:?}"),
+            PpmError::InvalidMaxValue(max_value) => {
+                write!(f, "max value {max_value} is outside 1 to 65535")
+            }
+            PpmError::ComponentTooLarge(component) => {
+                write!(f, "component {component} is larger than the max value")
+            }
+            PpmError::SizeExceedsData { width, height } => {
+                write!(f, "{width}x{height} pixels don't fit in the PPM's data")
+            }
+            PpmError::UnexpectedEnd => write!(f, "PPM ended before all pixels were read"),
+        }
+    }
+}
+
+impl std::error::Error for PpmError {}
+
+#[cfg(test)]
+mod tests {
+    use super::PpmError;
+    use crate::library::canvas::Canvas;
+    use crate::ray_tracer_challenge::{pt2, Rgb};
+
+    #[test]
+    fn constructing_the_ppm_header() {
+        let canvas: Canvas = Canvas::new(5, 3);
+        let ppm = canvas.to_ppm();
+        let header: Vec<&str> = ppm.lines().take(3).collect();
+        assert_eq!(header, vec!["P3", "5 3", "255"]);
+    }
+
+    #[test]
+    fn constructing_the_ppm_pixel_data() {
+        let mut canvas: Canvas = Canvas::new(5, 3);
+        canvas.set_pixel_rgb(pt2(0.0, 0.0), Rgb::new(1.5, 0.0, 0.0));
+        canvas.set_pixel_rgb(pt2(2.0, 1.0), Rgb::new(0.0, 0.5, 0.0));
+        canvas.set_pixel_rgb(pt2(4.0, 2.0), Rgb::new(-0.5, 0.0, 1.0));
+
+        let ppm = canvas.to_ppm();
+        let pixel_data: Vec<&str> = ppm.lines().skip(3).collect();
+        assert_eq!(
+            pixel_data,
+            vec![
+                "255 0 0 0 0 0 0 0 0 0 0 0 0 0 0",
+                "0 0 0 0 0 0 0 128 0 0 0 0 0 0 0",
+                "0 0 0 0 0 0 0 0 0 0 0 0 0 0 255",
+            ]
+        );
+    }
+
+    #[test]
+    fn splitting_long_lines_in_ppm_files() {
+        let mut canvas: Canvas = Canvas::new(10, 2);
+        for xy in canvas.iter_points() {
+            canvas.set_pixel_rgb(xy, Rgb::new(1.0, 0.8, 0.6));
+        }
+
+        let ppm = canvas.to_ppm();
+        let pixel_data: Vec<&str> = ppm.lines().skip(3).collect();
+        assert_eq!(
+            pixel_data,
+            vec![
+                "255 204 153 255 204 153 255 204 153 255 204 153 255 204 153 255 204",
+                "153 255 204 153 255 204 153 255 204 153 255 204 153",
+                "255 204 153 255 204 153 255 204 153 255 204 153 255 204 153 255 204",
+                "153 255 204 153 255 204 153 255 204 153 255 204 153",
+            ]
+        );
+    }
+
+    #[test]
+    fn ppm_files_are_terminated_by_a_newline_character() {
+        let canvas: Canvas = Canvas::new(5, 3);
+        let ppm = canvas.to_ppm();
+        assert!(ppm.ends_with('\n'));
+    }
+
+    #[test]
+    fn p6_ppm_files_store_raw_bytes_after_the_header() {
+        let mut canvas: Canvas = Canvas::new(2, 1);
+        canvas.set_pixel_rgb(pt2(1.0, 0.0), Rgb::new(1.0, 0.8, 0.6));
+
+        let ppm = canvas.to_ppm_p6();
+        let header = b"P6\n2 1\n255\n";
+        assert_eq!(&ppm[..header.len()], header);
+        assert_eq!(&ppm[header.len()..], &[0, 0, 0, 255, 204, 153]);
+    }
+
+    #[test]
+    fn reading_a_file_with_the_wrong_magic_number() {
+        let ppm = "P32\n1 1\n255\n0 0 0\n";
+        let result: Result<Canvas, PpmError> = Canvas::from_ppm(ppm.as_bytes());
+        assert_eq!(
+            result.err(),
+            Some(PpmError::UnsupportedMagicNumber("P32".into()))
+        );
+    }
+
+    #[test]
+    fn reading_a_ppm_returns_a_canvas_of_the_right_size() {
+        let ppm = "P3\n10 2\n255\n".to_string() + &"0 0 0\n".repeat(20);
+        let canvas: Canvas = Canvas::from_ppm(ppm.as_bytes()).expect("Failed to read PPM.");
+        assert_eq!(canvas.width(), 10.0);
+        assert_eq!(canvas.height(), 2.0);
+    }
+
+    #[test]
+    fn reading_pixel_data_from_a_ppm_file() {
+        let ppm = "P3
+4 3
+255
+255 127 0  0 127 255  127 255 0  255 255 255
+0 0 0  255 0 0  0 255 0  0 0 255
+255 255 0  0 255 255  255 0 255  127 127 127
+";
+        let canvas: Canvas = Canvas::from_ppm(ppm.as_bytes()).expect("Failed to read PPM.");
+        let expectations = [
+            (0.0, 0.0, Rgb::new(1.0, 0.49804, 0.0)),
+            (1.0, 0.0, Rgb::new(0.0, 0.49804, 1.0)),
+            (2.0, 0.0, Rgb::new(0.49804, 1.0, 0.0)),
+            (3.0, 0.0, Rgb::new(1.0, 1.0, 1.0)),
+            (0.0, 1.0, Rgb::new(0.0, 0.0, 0.0)),
+            (1.0, 1.0, Rgb::new(1.0, 0.0, 0.0)),
+            (2.0, 1.0, Rgb::new(0.0, 1.0, 0.0)),
+            (3.0, 1.0, Rgb::new(0.0, 0.0, 1.0)),
+            (0.0, 2.0, Rgb::new(1.0, 1.0, 0.0)),
+            (1.0, 2.0, Rgb::new(0.0, 1.0, 1.0)),
+            (2.0, 2.0, Rgb::new(1.0, 0.0, 1.0)),
+            (3.0, 2.0, Rgb::new(0.49804, 0.49804, 0.49804)),
+        ];
+        for (x, y, expected) in expectations {
+            assert_eq!(canvas.rgb_at(pt2(x, y)), Some(expected));
+        }
+    }
+
+    #[test]
+    fn ppm_parsing_ignores_comment_lines() {
+        let ppm = "P3
+# this is a comment
+2 1
+# this, too
+255
+# another comment
+255 255 255
+# oh, no, comments in the pixel data!
+255 0 255
+";
+        let canvas: Canvas = Canvas::from_ppm(ppm.as_bytes()).expect("Failed to read PPM.");
+        assert_eq!(canvas.rgb_at(pt2(0.0, 0.0)), Some(Rgb::new(1.0, 1.0, 1.0)));
+        assert_eq!(canvas.rgb_at(pt2(1.0, 0.0)), Some(Rgb::new(1.0, 0.0, 1.0)));
+    }
+
+    #[test]
+    fn ppm_parsing_allows_an_rgb_triple_to_span_lines() {
+        let ppm = "P3
+1 1
+255
+51
+153
+
+204
+";
+        let canvas: Canvas = Canvas::from_ppm(ppm.as_bytes()).expect("Failed to read PPM.");
+        assert_eq!(canvas.rgb_at(pt2(0.0, 0.0)), Some(Rgb::new(0.2, 0.6, 0.8)));
+    }
+
+    #[test]
+    fn ppm_parsing_respects_the_scale_setting() {
+        let ppm = "P3
+2 2
+100
+100 100 100  50 50 50
+75 50 25  0 0 0
+";
+        let canvas: Canvas = Canvas::from_ppm(ppm.as_bytes()).expect("Failed to read PPM.");
+        assert_eq!(
+            canvas.rgb_at(pt2(0.0, 1.0)),
+            Some(Rgb::new(0.75, 0.5, 0.25))
+        );
+    }
+
+    #[test]
+    fn reading_a_p6_ppm_file() {
+        let mut ppm = b"P6\n# comment\n2 1\n255\n".to_vec();
+        ppm.extend([255, 0, 51, 0, 10, 255]);
+        let canvas: Canvas = Canvas::from_ppm(&ppm).expect("Failed to read PPM.");
+        assert_eq!(canvas.rgb_at(pt2(0.0, 0.0)), Some(Rgb::new(1.0, 0.0, 0.2)));
+        assert_eq!(
+            canvas.rgb_at(pt2(1.0, 0.0)),
+            Some(Rgb::new(0.0, 10.0 / 255.0, 1.0))
+        );
+    }
+
+    #[test]
+    fn reading_a_p6_ppm_file_with_two_byte_components() {
+        let mut ppm = b"P6\n1 1\n1000\n".to_vec();
+        ppm.extend([0x03, 0xE8, 0x01, 0xF4, 0x00, 0x00]);
+        let canvas: Canvas = Canvas::from_ppm(&ppm).expect("Failed to read PPM.");
+        assert_eq!(canvas.rgb_at(pt2(0.0, 0.0)), Some(Rgb::new(1.0, 0.5, 0.0)));
+    }
+
+    #[test]
+    fn ppm_round_trips_through_p6() {
+        let mut canvas: Canvas = Canvas::new(3, 2);
+        canvas.set_pixel_rgb(pt2(2.0, 1.0), Rgb::new(1.0, 0.8, 0.6));
+
+        let round_tripped: Canvas =
+            Canvas::from_ppm(&canvas.to_ppm_p6()).expect("Failed to read PPM.");
+        assert_eq!(
+            round_tripped.rgb_at(pt2(2.0, 1.0)),
+            Some(Rgb::new(1.0, 0.8, 0.6))
+        );
+        assert_eq!(round_tripped.rgb_at(pt2(0.0, 0.0)), Some(Rgb::black()));
+    }
+
+    #[test]
+    fn reading_a_truncated_ppm_file() {
+        let ppm = "P3\n2 1\n255\n0 0 0 255\n";
+        let result: Result<Canvas, PpmError> = Canvas::from_ppm(ppm.as_bytes());
+        assert_eq!(result.err(), Some(PpmError::UnexpectedEnd));
+    }
+
+    #[test]
+    fn reading_a_ppm_whose_size_exceeds_its_data() {
+        let ppm = "P3\n100000 100000\n255\n0 0 0\n";
+        let result: Result<Canvas, PpmError> = Canvas::from_ppm(ppm.as_bytes());
+        assert_eq!(
+            result.err(),
+            Some(PpmError::SizeExceedsData {
+                width: 100000,
+                height: 100000
+            })
+        );
+
+        let mut ppm = b"P6\n2 1\n1000\n".to_vec();
+        ppm.extend([0; 6]);
+        let result: Result<Canvas, PpmError> = Canvas::from_ppm(&ppm);
+        assert_eq!(
+            result.err(),
+            Some(PpmError::SizeExceedsData {
+                width: 2,
+                height: 1
+            })
+        );
+    }
+}
@@ -0,0 +1,324 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
+
+use crate::library::canvas::Canvas;
+use crate::library::framebuffer::{Framebuffer, RgbFramebuffer};
+use crate::ray_tracer_challenge::{pt2, Point2, Rgb};
+
+/// A rectangle of pixels, in canvas coordinates.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TileBounds {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl TileBounds {
+    /// Iterates row by row, starting at the top left.
+    pub fn iter_points(&self) -> impl Iterator<Item = Point2> {
+        let TileBounds {
+            x,
+            y,
+            width,
+            height,
+        } = *self;
+        (y..y + height).flat_map(move |y| (x..x + width).map(move |x| pt2(x as f32, y as f32)))
+    }
+}
+
+/// A finished tile. `rgbs` are in the same order as `bounds.iter_points()`.
+pub struct Tile {
+    pub bounds: TileBounds,
+    pub rgbs: Vec<Rgb>,
+}
+
+impl Tile {
+    /// Scales the tile up by `factor`, turning each pixel into a `factor` by
+    /// `factor` block. The result is cropped to `max_width` by `max_height`.
+    pub fn upscale(&self, factor: u32, max_width: u32, max_height: u32) -> Tile {
+        let x = self.bounds.x * factor;
+        let y = self.bounds.y * factor;
+        let bounds = TileBounds {
+            x,
+            y,
+            width: (self.bounds.width * factor).min(max_width.saturating_sub(x)),
+            height: (self.bounds.height * factor).min(max_height.saturating_sub(y)),
+        };
+        let rgbs = bounds
+            .iter_points()
+            .map(|xy| {
+                let source_x = (xy.x() as u32 - x) / factor;
+                let source_y = (xy.y() as u32 - y) / factor;
+                self.rgbs[(source_y * self.bounds.width + source_x) as usize]
+            })
+            .collect();
+        Tile { bounds, rgbs }
+    }
+}
+
+impl Canvas<RgbFramebuffer> {
+    /// Adds the tile's colors as another sample of each pixel, rather than
+    /// replacing them.
+    pub fn add_tile(&mut self, tile: &Tile) {
+        for (xy, rgb) in tile.bounds.iter_points().zip(&tile.rgbs) {
+            self.add_sample(xy, *rgb);
+        }
+    }
+}
+
+impl<F: Framebuffer> Canvas<F> {
+    pub fn set_tile(&mut self, tile: &Tile) {
+        for (xy, rgb) in tile.bounds.iter_points().zip(&tile.rgbs) {
+            self.set_pixel_rgb(xy, *rgb);
+        }
+    }
+}
+
+/// Renders an image by splitting it into square tiles and shading them on a
+/// pool of worker threads.
+///
+/// Each pixel is shaded by calling `shade` with the pixel's coordinates, so as
+/// long as `shade` only depends on its argument, the result is the same no
+/// matter how many threads are used or which order tiles finish in.
+pub struct TileRenderer {
+    tile_size: u32,
+    num_threads: usize,
+}
+
+impl TileRenderer {
+    pub fn new() -> Self {
+        let num_threads = thread::available_parallelism().map_or(1, |n| n.get());
+        TileRenderer {
+            tile_size: 32,
+            num_threads,
+        }
+    }
+
+    pub fn with_tile_size(mut self, tile_size: u32) -> Self {
+        self.tile_size = tile_size.max(1);
+        self
+    }
+
+    pub fn with_num_threads(mut self, num_threads: usize) -> Self {
+        self.num_threads = num_threads.max(1);
+        self
+    }
+
+    /// Splits a `width` by `height` image into tiles, row by row. Tiles on the
+    /// right and bottom edges are cropped to fit.
+    pub fn tiles(&self, width: u32, height: u32) -> Vec<TileBounds> {
+        let tile_size = self.tile_size;
+        (0..height)
+            .step_by(tile_size as usize)
+            .flat_map(|y| {
+                (0..width)
+                    .step_by(tile_size as usize)
+                    .map(move |x| TileBounds {
+                        x,
+                        y,
+                        width: tile_size.min(width - x),
+                        height: tile_size.min(height - y),
+                    })
+            })
+            .collect()
+    }
+
+    /// Shades every pixel on the canvas, overwriting what was there.
+    pub fn render<F, S>(&self, canvas: &mut Canvas<F>, shade: S)
+    where
+        F: Framebuffer,
+        S: Fn(Point2) -> Rgb + Sync,
+    {
+        let width = canvas.width() as u32;
+        let height = canvas.height() as u32;
+        self.render_tiles(width, height, shade, |tile| canvas.set_tile(&tile));
+    }
+
+    /// Renders the image several times, starting with one sample per 8x8 block
+    /// of pixels and halving the block size each pass until every pixel has
+    /// been shaded. Each pass costs a quarter of the next, so a rough preview
+    /// is ready almost immediately.
+    pub fn render_progressively<S, T>(&self, width: u32, height: u32, shade: S, mut on_tile: T)
+    where
+        S: Fn(Point2) -> Rgb + Sync,
+        T: FnMut(Tile),
+    {
+        for block_size in [8, 4, 2, 1] {
+            let pass_width = width.div_ceil(block_size);
+            let pass_height = height.div_ceil(block_size);
+            let block_size_f32 = block_size as f32;
+            let shade_block =
+                |xy: Point2| shade(pt2(xy.x() * block_size_f32, xy.y() * block_size_f32));
+            self.render_tiles(pass_width, pass_height, shade_block, |tile| {
+                on_tile(tile.upscale(block_size, width, height));
+            });
+        }
+    }
+
+    /// Shades a `width` by `height` image, calling `on_tile` on the calling
+    /// thread as each tile finishes. Tiles can finish in any order.
+    pub fn render_tiles<S, T>(&self, width: u32, height: u32, shade: S, mut on_tile: T)
+    where
+        S: Fn(Point2) -> Rgb + Sync,
+        T: FnMut(Tile),
+    {
+        let tiles = self.tiles(width, height);
+        let next_tile_index = AtomicUsize::new(0);
+        let (sender, receiver) = mpsc::channel();
+
+        thread::scope(|scope| {
+            for _ in 0..self.num_threads.min(tiles.len()) {
+                let sender = sender.clone();
+                let tiles = &tiles;
+                let next_tile_index = &next_tile_index;
+                let shade = &shade;
+                scope.spawn(move || {
+                    while let Some(bounds) =
+                        tiles.get(next_tile_index.fetch_add(1, Ordering::Relaxed))
+                    {
+                        let rgbs = bounds.iter_points().map(shade).collect();
+                        let tile = Tile {
+                            bounds: *bounds,
+                            rgbs,
+                        };
+                        if sender.send(tile).is_err() {
+                            return;
+                        }
+                    }
+                });
+            }
+            // Drop our sender so the loop below ends once every worker is done.
+            drop(sender);
+
+            for tile in receiver {
+                on_tile(tile);
+            }
+        });
+    }
+}
+
+impl Default for TileRenderer {
+    fn default() -> Self {
+        TileRenderer::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shade(xy: Point2) -> Rgb {
+        Rgb::new(xy.x() / 100.0, xy.y() / 100.0, (xy.x() * xy.y()).sin())
+    }
+
+    #[test]
+    fn tiles_cover_every_pixel_exactly_once() {
+        let renderer = TileRenderer::new().with_tile_size(16);
+        let tiles = renderer.tiles(37, 20);
+        assert_eq!(tiles.len(), 6);
+        assert_eq!(
+            tiles[5],
+            TileBounds {
+                x: 32,
+                y: 16,
+                width: 5,
+                height: 4,
+            }
+        );
+
+        let mut counts = vec![0; 37 * 20];
+        for xy in tiles.iter().flat_map(|tile| tile.iter_points()) {
+            counts[xy.y() as usize * 37 + xy.x() as usize] += 1;
+        }
+        assert!(counts.iter().all(|&count| count == 1));
+    }
+
+    #[test]
+    fn renders_the_same_image_as_shading_each_pixel_in_turn() {
+        let mut expected: Canvas = Canvas::new(37, 20);
+        for xy in expected.iter_points() {
+            expected.set_pixel_rgb(xy, shade(xy));
+        }
+
+        let mut actual: Canvas = Canvas::new(37, 20);
+        TileRenderer::new()
+            .with_tile_size(8)
+            .render(&mut actual, shade);
+
+        for xy in expected.iter_points() {
+            assert_eq!(actual.rgb_at(xy), expected.rgb_at(xy));
+        }
+    }
+
+    #[test]
+    fn output_does_not_depend_on_the_number_of_threads() {
+        let mut single_threaded: Canvas = Canvas::new(50, 30);
+        TileRenderer::new()
+            .with_num_threads(1)
+            .render(&mut single_threaded, shade);
+
+        let mut multi_threaded: Canvas = Canvas::new(50, 30);
+        TileRenderer::new()
+            .with_num_threads(7)
+            .with_tile_size(4)
+            .render(&mut multi_threaded, shade);
+
+        let mut single_threaded_frame = vec![0; 50 * 30 * 4];
+        single_threaded.write_frame(&mut single_threaded_frame);
+        let mut multi_threaded_frame = vec![0; 50 * 30 * 4];
+        multi_threaded.write_frame(&mut multi_threaded_frame);
+        assert_eq!(single_threaded_frame, multi_threaded_frame);
+    }
+
+    #[test]
+    fn upscaling_a_tile_crops_it_to_the_image() {
+        let tile = Tile {
+            bounds: TileBounds {
+                x: 1,
+                y: 0,
+                width: 2,
+                height: 1,
+            },
+            rgbs: vec![Rgb::new(1.0, 0.0, 0.0), Rgb::new(0.0, 1.0, 0.0)],
+        };
+        let upscaled = tile.upscale(2, 5, 1);
+        assert_eq!(
+            upscaled.bounds,
+            TileBounds {
+                x: 2,
+                y: 0,
+                width: 3,
+                height: 1,
+            }
+        );
+        assert_eq!(
+            upscaled.rgbs,
+            vec![
+                Rgb::new(1.0, 0.0, 0.0),
+                Rgb::new(1.0, 0.0, 0.0),
+                Rgb::new(0.0, 1.0, 0.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn the_last_progressive_pass_matches_a_full_render() {
+        let mut expected: Canvas = Canvas::new(37, 20);
+        TileRenderer::new().render(&mut expected, shade);
+
+        let mut actual: Canvas = Canvas::new(37, 20);
+        let mut num_pixels_written = 0;
+        TileRenderer::new().render_progressively(37, 20, shade, |tile| {
+            num_pixels_written += tile.rgbs.len();
+            actual.set_tile(&tile);
+        });
+
+        // Every pass covers the whole image.
+        assert_eq!(num_pixels_written, 4 * 37 * 20);
+        for xy in expected.iter_points() {
+            assert_eq!(actual.rgb_at(xy), expected.rgb_at(xy));
+        }
+    }
+}
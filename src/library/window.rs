@@ -1,20 +1,25 @@
-use crate::crate_wrappers::pixels::Pixels;
+use crate::crate_wrappers::pixels::{Pixels, SurfaceTexture};
+use crate::library::background_render::BackgroundRender;
 use crate::library::canvas::Canvas;
 use crate::Sketch;
 use log::error;
+use std::time::{Duration, Instant};
 use tao::dpi::LogicalSize;
 use tao::event::{Event, KeyEvent, WindowEvent};
 use tao::event_loop::{ControlFlow, EventLoop};
 use tao::keyboard::KeyCode;
 use tao::window::WindowBuilder;
 
+/// How often the window checks for newly rendered tiles while a render is running.
+const PREVIEW_INTERVAL: Duration = Duration::from_millis(16);
+
 pub struct Window {
     event_loop: EventLoop<()>,
     pub canvas: Canvas,
+    pixels: Pixels,
 
     // We need to hold on to this because if we drop it,
     // Tao doesn't actually render the window.
-    #[allow(dead_code)]
     tao_window: tao::window::Window,
 }
 
@@ -33,38 +38,37 @@ impl Window {
                 .unwrap()
         };
 
-        let canvas = {
-            #[cfg(not(test))]
-            let pixels = {
-                use crate::crate_wrappers::pixels::SurfaceTexture;
-
-                let window_size = tao_window.inner_size();
-                let surface_texture =
-                    SurfaceTexture::new(window_size.width, window_size.height, &tao_window);
-                let maybe_pixels = Pixels::new(width, height, surface_texture);
-                match maybe_pixels {
-                    Ok(pixels) => pixels,
-                    Err(err) => {
-                        log_error("Pixels::new", err);
-                        panic!("Error creating Pixels");
-                    }
+        let pixels = {
+            let window_size = tao_window.inner_size();
+            let surface_texture =
+                SurfaceTexture::new(window_size.width, window_size.height, &tao_window);
+            let maybe_pixels = Pixels::new(width, height, surface_texture);
+            match maybe_pixels {
+                Ok(pixels) => pixels,
+                Err(err) => {
+                    log_error("Pixels::new", err);
+                    panic!("Error creating Pixels");
                 }
-            };
-
-            #[cfg(test)]
-            let pixels = Pixels::new(width, height);
-
-            Canvas::new(width, height, pixels)
+            }
         };
 
+        let canvas = Canvas::new(width, height);
+
         Window {
             event_loop,
             tao_window,
             canvas,
+            pixels,
         }
     }
 
+    /// Renders the sketch on a background thread, showing tiles as they finish.
     pub fn run_event_loop(mut self, sketch: Sketch) {
+        let width = self.canvas.width() as u32;
+        let height = self.canvas.height() as u32;
+        let mut background_render =
+            BackgroundRender::spawn(move |on_tile| sketch.render(width, height, on_tile));
+
         self.event_loop.run(move |event, _, control_flow| {
             match event {
                 Event::WindowEvent { event, .. } => match event {
@@ -83,8 +87,7 @@ impl Window {
 
                     // Resize the window
                     WindowEvent::Resized(size) => {
-                        if let Err(err) = self.canvas.pixels.resize_surface(size.width, size.height)
-                        {
+                        if let Err(err) = self.pixels.resize_surface(size.width, size.height) {
                             log_error("pixels.resize_surface", err);
                             *control_flow = ControlFlow::Exit;
                         }
@@ -93,16 +96,24 @@ impl Window {
                     _ => {}
                 },
 
-                // Update internal state and request a redraw
+                // Pick up any newly rendered tiles and request a redraw
                 Event::MainEventsCleared => {
-                    // world.update();
-                    // window.request_redraw();
+                    if background_render.receive_tiles(&mut self.canvas) {
+                        self.tao_window.request_redraw();
+                    }
+                    // Once the render's done there's nothing to poll for. Exit is
+                    // sticky, so this can't cancel a close request.
+                    *control_flow = if background_render.is_finished() {
+                        ControlFlow::Wait
+                    } else {
+                        ControlFlow::WaitUntil(Instant::now() + PREVIEW_INTERVAL)
+                    };
                 }
 
                 // Draw the current frame
                 Event::RedrawRequested(_) => {
-                    sketch.draw(&mut self.canvas);
-                    if let Err(err) = self.canvas.pixels.render() {
+                    self.canvas.write_frame(self.pixels.frame_mut());
+                    if let Err(err) = self.pixels.render() {
                         log_error("pixels.render", err);
                         *control_flow = ControlFlow::Exit;
                     }
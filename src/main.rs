@@ -1,6 +1,7 @@
 #![deny(clippy::all)]
 #![forbid(unsafe_code)]
 
+use camino::Utf8PathBuf;
 use library::window::Window;
 use prelude::*;
 use sketch::Sketch;
@@ -12,9 +13,41 @@ mod prelude;
 mod ray_tracer_challenge;
 mod sketch;
 
+const USAGE: &str = "Usage:
+  vello-vs-pixels                                    Open a window and draw the sketch.
+  vello-vs-pixels headless <output> <width> <height> Draw the sketch and save it to <output>.";
+
 fn main() {
     env_logger::init();
-    let window = Window::new(CANVAS_WIDTH.round() as u32, CANVAS_HEIGHT.round() as u32);
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        None => {
+            let window = Window::new(CANVAS_WIDTH.round() as u32, CANVAS_HEIGHT.round() as u32);
+            let sketch = Sketch::new();
+            window.run_event_loop(sketch);
+        }
+        Some("headless") => run_headless(&args[1..]),
+        Some(_) => exit_with_usage(),
+    }
+}
+
+fn run_headless(args: &[String]) {
+    let [output, width, height] = args else {
+        exit_with_usage();
+    };
+    let path = Utf8PathBuf::from(output);
+    let (Ok(width), Ok(height)) = (width.parse::<u32>(), height.parse::<u32>()) else {
+        exit_with_usage();
+    };
+
     let sketch = Sketch::new();
-    window.run_event_loop(sketch);
+    if let Err(err) = library::headless::render_to_file(&sketch, &path, width, height) {
+        eprintln!("Failed to save {path}: {err}");
+        std::process::exit(1);
+    }
+}
+
+fn exit_with_usage() -> ! {
+    eprintln!("{USAGE}");
+    std::process::exit(2);
 }
@@ -0,0 +1,215 @@
+use crate::library::canvas::Canvas;
+use crate::library::tile_renderer::TileRenderer;
+
+use rand::Rng;
+
+use super::{pt2, rng_for_pixel, Camera, Ray, Rgb};
+
+/// Which pixels differ from a neighbor by more than a threshold, measured as
+/// Euclidean distance in Oklab.
+pub struct ContrastMask {
+    width: u32,
+    height: u32,
+    is_high_contrast: Vec<bool>,
+}
+
+impl ContrastMask {
+    /// Compares each pixel with the eight pixels around it.
+    pub fn new(canvas: &Canvas, threshold: f32) -> ContrastMask {
+        let width = canvas.width() as u32;
+        let height = canvas.height() as u32;
+        let rgb_at = |x: i64, y: i64| canvas.rgb_at(pt2(x as f32, y as f32));
+
+        let mut is_high_contrast = Vec::with_capacity((width * height) as usize);
+        for y in 0..height as i64 {
+            for x in 0..width as i64 {
+                let rgb = rgb_at(x, y).unwrap_or_else(Rgb::black);
+                let differs_from_a_neighbor = (-1..=1)
+                    .flat_map(|dy| (-1..=1).map(move |dx| (x + dx, y + dy)))
+                    .filter(|&neighbor| neighbor != (x, y))
+                    .filter_map(|(x, y)| rgb_at(x, y))
+                    .any(|neighbor| rgb.oklab_distance(neighbor) > threshold);
+                is_high_contrast.push(differs_from_a_neighbor);
+            }
+        }
+
+        ContrastMask {
+            width,
+            height,
+            is_high_contrast,
+        }
+    }
+
+    pub fn is_high_contrast(&self, x: u32, y: u32) -> bool {
+        x < self.width && y < self.height && self.is_high_contrast[(y * self.width + x) as usize]
+    }
+
+    pub fn count(&self) -> usize {
+        self.is_high_contrast
+            .iter()
+            .filter(|&&is_high_contrast| is_high_contrast)
+            .count()
+    }
+}
+
+impl Camera {
+    /// Renders one sample through the center of each pixel, from a random
+    /// point on the lens at a random time while the shutter is open, then
+    /// re-renders only the pixels that contrast with a neighbor by more than
+    /// `contrast_threshold`, using the camera's `sampling`. Edges come out
+    /// about as smooth as supersampling everything, for a fraction of the cost.
+    ///
+    /// Oklab distances of around 0.02 are barely noticeable; 0.05 is a good
+    /// place to start.
+    pub fn render_adaptive(
+        &self,
+        contrast_threshold: f32,
+        color_for_ray: impl Fn(Ray) -> Rgb + Sync,
+    ) -> Canvas {
+        let renderer = TileRenderer::new();
+
+        let mut canvas = Canvas::new(self.hsize(), self.vsize());
+        renderer.render(&mut canvas, |xy| {
+            let x = xy.x() as u32;
+            let y = xy.y() as u32;
+            let mut rng = rng_for_pixel(x, y, 1);
+            let lens_sample = pt2(rng.gen(), rng.gen());
+            let time = self.shutter_time(rng.gen());
+            let ray = self
+                .ray_for_pixel_through_lens(x, y, pt2(0.5, 0.5), lens_sample)
+                .with_time(time);
+            color_for_ray(ray)
+        });
+
+        let mask = ContrastMask::new(&canvas, contrast_threshold);
+        let mut refined = Canvas::new(self.hsize(), self.vsize());
+        renderer.render(&mut refined, |xy| {
+            let x = xy.x() as u32;
+            let y = xy.y() as u32;
+            if mask.is_high_contrast(x, y) {
+                self.render_pixel(x, y, &color_for_ray)
+            } else {
+                canvas.rgb_at(xy).unwrap_or_else(Rgb::black)
+            }
+        });
+        refined
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{
+        pt3, vec3, Lens, Material, Motion, ReconstructionFilter, SamplePattern, Sampling, Shape,
+        Transform, World,
+    };
+    use super::*;
+    use std::f32::consts::PI;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// On an 11 pixel wide canvas, the edge runs down column 5, a little to
+    /// the left of the pixels' centers.
+    fn mostly_white_on_the_right(ray: Ray) -> Rgb {
+        if ray.direction.x() < 0.04 {
+            Rgb::new(1.0, 1.0, 1.0)
+        } else {
+            Rgb::black()
+        }
+    }
+
+    #[test]
+    fn flat_regions_are_not_high_contrast() {
+        let mut canvas: Canvas = Canvas::new(5, 5);
+        for xy in canvas.iter_points() {
+            canvas.set_pixel_rgb(xy, Rgb::new(0.5, 0.5, 0.5));
+        }
+        canvas.set_pixel_rgb(pt2(0.0, 0.0), Rgb::new(0.51, 0.5, 0.5));
+
+        let mask = ContrastMask::new(&canvas, 0.05);
+        assert_eq!(mask.count(), 0);
+    }
+
+    #[test]
+    fn pixels_next_to_an_edge_are_high_contrast() {
+        let mut canvas: Canvas = Canvas::new(5, 5);
+        canvas.set_pixel_rgb(pt2(2.0, 2.0), Rgb::new(1.0, 1.0, 1.0));
+
+        let mask = ContrastMask::new(&canvas, 0.05);
+        assert_eq!(mask.count(), 9);
+        assert!(mask.is_high_contrast(1, 1));
+        assert!(mask.is_high_contrast(2, 2));
+        assert!(!mask.is_high_contrast(0, 0));
+        assert!(!mask.is_high_contrast(5, 5));
+    }
+
+    #[test]
+    fn only_high_contrast_pixels_are_supersampled() {
+        let mut camera = Camera::new(11, 11, PI / 2.0);
+        camera.sampling = Sampling::new(SamplePattern::Jittered, 16, ReconstructionFilter::Box);
+
+        let num_rays = AtomicUsize::new(0);
+        let canvas = camera.render_adaptive(0.05, |ray| {
+            num_rays.fetch_add(1, Ordering::Relaxed);
+            mostly_white_on_the_right(ray)
+        });
+
+        // Column 5's centers are white and column 4's are black, so those two
+        // columns are the only ones that contrast with a neighbor.
+        let num_supersampled_pixels = 2 * 11;
+        assert_eq!(
+            num_rays.into_inner(),
+            11 * 11 + num_supersampled_pixels * 16
+        );
+
+        let edge = canvas.rgb_at(pt2(5.0, 5.0)).unwrap();
+        assert!(edge.red() > 0.5 && edge.red() < 0.9, "{edge:?}");
+        assert_eq!(canvas.rgb_at(pt2(4.0, 5.0)), Some(Rgb::black()));
+        assert_eq!(canvas.rgb_at(pt2(0.0, 5.0)), Some(Rgb::black()));
+        assert_eq!(canvas.rgb_at(pt2(10.0, 5.0)), Some(Rgb::new(1.0, 1.0, 1.0)));
+    }
+
+    #[test]
+    fn the_first_pass_traces_through_the_lens() {
+        let mut camera = Camera::new(11, 11, PI / 2.0);
+        camera.lens = Lens::new(0.5, 5.0);
+
+        // Nothing contrasts by more than this, so every ray is from the first pass.
+        let num_pinhole_rays = AtomicUsize::new(0);
+        camera.render_adaptive(10.0, |ray| {
+            if ray.origin == pt3(0.0, 0.0, 0.0) {
+                num_pinhole_rays.fetch_add(1, Ordering::Relaxed);
+            }
+            Rgb::black()
+        });
+        assert_eq!(num_pinhole_rays.into_inner(), 0);
+    }
+
+    #[test]
+    fn the_first_pass_traces_while_the_shutter_is_open() {
+        let motion = Motion::linear(
+            Transform::translation(-2.0, 0.0, 0.0),
+            Transform::translation(2.0, 0.0, 0.0),
+        );
+        let world = World {
+            objects: vec![Shape::sphere().with_motion(motion).with_material(Material {
+                emission: Rgb::new(1.0, 1.0, 1.0),
+                ..Material::default()
+            })],
+            lights: vec![],
+            ..World::default()
+        };
+        let mut camera = Camera::new(21, 21, PI / 2.0);
+        camera.set_transform(Transform::view_transform(
+            pt3(0.0, 0.0, -5.0),
+            pt3(0.0, 0.0, 0.0),
+            vec3(0.0, 1.0, 0.0),
+        ));
+        camera.shutter_close = 1.0;
+
+        // The sphere sweeps across the middle row, so it shows up on both
+        // sides of the center only if rays are cast at more than one time.
+        let canvas = camera.render_adaptive(10.0, |ray| world.color_at(ray));
+        let is_hit = |x: u32| canvas.rgb_at(pt2(x as f32, 10.0)) != Some(Rgb::black());
+        assert!((0..8).any(is_hit));
+        assert!((13..21).any(is_hit));
+    }
+}
@@ -0,0 +1,300 @@
+use rand::Rng;
+
+use super::{rng_for_point, Attenuation, Light, LightSample, Point3, Rgb, Vec3};
+
+/// A rectangular light made of a grid of `usteps` by `vsteps` cells. Shadows
+/// are cast from one point in each cell, so a surface that can only see some
+/// of the cells is partly lit, which gives shadows soft edges.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AreaLight {
+    pub corner: Point3,
+    /// One cell's edge along the first side of the rectangle.
+    pub uvec: Vec3,
+    pub usteps: u32,
+    /// One cell's edge along the second side of the rectangle.
+    pub vvec: Vec3,
+    pub vsteps: u32,
+    pub intensity: Rgb,
+    /// Whether to sample a random point in each cell rather than its center.
+    /// Jittering trades the banding you'd get from a small grid for noise.
+    pub jitter: bool,
+    /// Applied to each sample by its own distance.
+    pub attenuation: Attenuation,
+}
+
+impl AreaLight {
+    /// A light covering the parallelogram from `corner` along `full_uvec` and
+    /// `full_vvec`, split into `usteps` by `vsteps` cells. Jittered by default.
+    pub fn new(
+        corner: Point3,
+        full_uvec: Vec3,
+        usteps: u32,
+        full_vvec: Vec3,
+        vsteps: u32,
+        intensity: Rgb,
+    ) -> AreaLight {
+        let usteps = usteps.max(1);
+        let vsteps = vsteps.max(1);
+        AreaLight {
+            corner,
+            uvec: full_uvec / usteps as f32,
+            usteps,
+            vvec: full_vvec / vsteps as f32,
+            vsteps,
+            intensity,
+            jitter: true,
+            attenuation: Attenuation::none(),
+        }
+    }
+
+    pub fn with_jitter(mut self, jitter: bool) -> AreaLight {
+        self.jitter = jitter;
+        self
+    }
+
+    pub fn with_attenuation(mut self, attenuation: Attenuation) -> AreaLight {
+        self.attenuation = attenuation;
+        self
+    }
+
+    pub fn num_samples(&self) -> u32 {
+        self.usteps * self.vsteps
+    }
+
+    /// The center of the light.
+    pub fn position(&self) -> Point3 {
+        let half_u = self.uvec * (self.usteps as f32 / 2.0);
+        let half_v = self.vvec * (self.vsteps as f32 / 2.0);
+        self.corner + half_u + half_v
+    }
+
+    /// The point `offset` of the way across cell `(u, v)`, where `(0.5, 0.5)`
+    /// is the cell's center.
+    pub fn point_on_light(&self, u: u32, v: u32, offset: (f32, f32)) -> Point3 {
+        self.corner + self.uvec * (u as f32 + offset.0) + self.vvec * (v as f32 + offset.1)
+    }
+
+    /// One point in each cell. When jittering, the points are random but
+    /// always the same for the same `shading_point`, so renders are repeatable.
+    pub fn sample_points(&self, shading_point: Point3) -> Vec<Point3> {
+        let mut rng = rng_for_point(shading_point, 0);
+        let mut samples = Vec::with_capacity(self.num_samples() as usize);
+        for v in 0..self.vsteps {
+            for u in 0..self.usteps {
+                let offset = if self.jitter {
+                    (rng.gen(), rng.gen())
+                } else {
+                    (0.5, 0.5)
+                };
+                samples.push(self.point_on_light(u, v, offset));
+            }
+        }
+        samples
+    }
+}
+
+impl Light for AreaLight {
+    fn intensity(&self) -> Rgb {
+        self.intensity
+    }
+
+    fn attenuation(&self) -> Attenuation {
+        self.attenuation
+    }
+
+    fn samples(&self, point: Point3) -> Vec<LightSample> {
+        self.sample_points(point)
+            .into_iter()
+            .map(|sample_point| LightSample::toward(point, sample_point))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{
+        default_world, lighting, pt3, vec3, Camera, Material, PointLight, Shape, Transform,
+    };
+    use super::*;
+    use crate::extensions::F32Extension;
+    use crate::library::image_comparison::assert_scene_matches_golden_image;
+    use camino::Utf8Path;
+    use std::f32::consts::{FRAC_1_SQRT_2, PI};
+
+    fn white() -> Rgb {
+        Rgb::new(1.0, 1.0, 1.0)
+    }
+
+    #[test]
+    fn creating_an_area_light() {
+        let corner = pt3(0.0, 0.0, 0.0);
+        let light = AreaLight::new(
+            corner,
+            vec3(2.0, 0.0, 0.0),
+            4,
+            vec3(0.0, 0.0, 1.0),
+            2,
+            white(),
+        );
+        assert_eq!(light.corner, corner);
+        assert_eq!(light.uvec, vec3(0.5, 0.0, 0.0));
+        assert_eq!(light.usteps, 4);
+        assert_eq!(light.vvec, vec3(0.0, 0.0, 0.5));
+        assert_eq!(light.vsteps, 2);
+        assert_eq!(light.num_samples(), 8);
+        assert_eq!(light.position(), pt3(1.0, 0.0, 0.5));
+    }
+
+    #[test]
+    fn finding_a_single_point_on_an_area_light() {
+        let corner = pt3(0.0, 0.0, 0.0);
+        let light = AreaLight::new(
+            corner,
+            vec3(2.0, 0.0, 0.0),
+            4,
+            vec3(0.0, 0.0, 1.0),
+            2,
+            white(),
+        );
+        let cases = [
+            (0, 0, pt3(0.25, 0.0, 0.25)),
+            (1, 0, pt3(0.75, 0.0, 0.25)),
+            (0, 1, pt3(0.25, 0.0, 0.75)),
+            (2, 0, pt3(1.25, 0.0, 0.25)),
+            (3, 1, pt3(1.75, 0.0, 0.75)),
+        ];
+        for (u, v, expected) in cases {
+            assert_eq!(light.point_on_light(u, v, (0.5, 0.5)), expected);
+        }
+    }
+
+    #[test]
+    fn jittered_samples_stay_in_their_cells_and_are_repeatable() {
+        let corner = pt3(0.0, 0.0, 0.0);
+        let light = AreaLight::new(
+            corner,
+            vec3(2.0, 0.0, 0.0),
+            4,
+            vec3(0.0, 0.0, 1.0),
+            2,
+            white(),
+        );
+        let point = pt3(1.0, -1.0, 2.0);
+        let samples = light.sample_points(point);
+        for (index, sample) in samples.iter().enumerate() {
+            let u = (index % 4) as f32;
+            let v = (index / 4) as f32;
+            assert!(sample.x() >= u * 0.5 && sample.x() < (u + 1.0) * 0.5);
+            assert!(sample.z() >= v * 0.5 && sample.z() < (v + 1.0) * 0.5);
+        }
+        assert_eq!(light.sample_points(point), samples);
+        assert_ne!(light.sample_points(pt3(1.0, -1.0, 2.5)), samples);
+    }
+
+    #[test]
+    fn the_area_light_intensity_function() {
+        let world = default_world();
+        let corner = pt3(-0.5, -0.5, -5.0);
+        let light = AreaLight::new(
+            corner,
+            vec3(1.0, 0.0, 0.0),
+            2,
+            vec3(0.0, 1.0, 0.0),
+            2,
+            white(),
+        )
+        .with_jitter(false);
+        let cases = [
+            (pt3(0.0, 0.0, 2.0), 0.0),
+            (pt3(1.0, -1.0, 2.0), 0.25),
+            (pt3(1.5, 0.0, 2.0), 0.5),
+            (pt3(1.25, 1.25, 3.0), 0.75),
+            (pt3(0.0, 0.0, -2.0), 1.0),
+        ];
+        for (point, expected) in cases {
+            assert_eq!(
+                light.intensity_at(point, 0.0, &world),
+                expected,
+                "{point:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn lighting_uses_light_intensity_to_attenuate_color() {
+        let world = default_world();
+        let light = PointLight::new(pt3(0.0, 0.0, -10.0), white());
+        let material = Material {
+            ambient: 0.1,
+            diffuse: 0.9,
+            specular: 0.0,
+            color: white(),
+            ..world.objects[0].material.clone()
+        };
+        let point = pt3(0.0, 0.0, -1.0);
+        let eyev = vec3(0.0, 0.0, -1.0);
+        let normalv = vec3(0.0, 0.0, -1.0);
+        let cases = [(1.0, 1.0), (0.5, 0.55), (0.0, 0.1)];
+        for (intensity, expected) in cases {
+            let actual = lighting(&material, &light, point, eyev, normalv, intensity);
+            assert_eq!(actual, Rgb::new(expected, expected, expected));
+        }
+    }
+
+    #[test]
+    fn lighting_samples_the_area_light() {
+        let corner = pt3(-0.5, -0.5, -5.0);
+        let light = AreaLight::new(
+            corner,
+            vec3(1.0, 0.0, 0.0),
+            2,
+            vec3(0.0, 1.0, 0.0),
+            2,
+            white(),
+        )
+        .with_jitter(false);
+        let material = Material {
+            ambient: 0.1,
+            diffuse: 0.9,
+            specular: 0.0,
+            color: white(),
+            ..Material::default()
+        };
+        let sphere = Shape::sphere().with_material(material);
+        let eye = pt3(0.0, 0.0, -5.0);
+        let cases = [
+            (pt3(0.0, 0.0, -1.0), 0.9965),
+            (pt3(0.0, FRAC_1_SQRT_2, -FRAC_1_SQRT_2), 0.62318),
+        ];
+        for (point, expected) in cases {
+            let eyev = (eye - point).normalize();
+            let normalv = vec3(point.x(), point.y(), point.z());
+            let actual = lighting(&sphere.material, &light, point, eyev, normalv, 1.0);
+            assert!((actual.red() - expected).abs() < 0.0001, "{actual:?}");
+            assert!(actual.red().almost_eq(actual.green()));
+        }
+    }
+
+    #[test]
+    fn soft_shadows_match_golden_image() {
+        let mut world = default_world();
+        let floor = Shape::plane().with_transform(Transform::translation(0.0, -1.0, 0.0));
+        world.objects.push(floor);
+        let corner = pt3(-3.0, 4.0, -3.0);
+        world.lights = vec![Box::new(AreaLight::new(
+            corner,
+            vec3(2.0, 0.0, 0.0),
+            4,
+            vec3(0.0, 2.0, 0.0),
+            4,
+            Rgb::new(1.5, 1.5, 1.5),
+        ))];
+
+        assert_scene_matches_golden_image(
+            |ray| world.color_at(ray),
+            pt3(0.0, 1.5, -6.0),
+            pt3(0.0, -0.5, 0.0),
+            Utf8Path::new("src/tests/soft_shadows_expected_output.png"),
+        );
+    }
+}
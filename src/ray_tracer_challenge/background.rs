@@ -0,0 +1,291 @@
+use std::f32::consts::{PI, TAU};
+
+use camino::Utf8Path;
+use image::ImageResult;
+
+use crate::library::canvas::Canvas;
+
+use super::{vec3, CubeMap, Rgb, Texture, TextureWrap, Vec3};
+
+/// What rays that miss everything see.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum Background {
+    Constant(Rgb),
+    /// Blends from `bottom`, straight down, to `top`, straight up.
+    VerticalGradient {
+        bottom: Rgb,
+        top: Rgb,
+    },
+    Environment(EnvironmentMap),
+    /// A skybox.
+    CubeMap(CubeMap),
+    Sky(PreethamSky),
+    /// Black.
+    #[default]
+    None,
+}
+
+impl Background {
+    /// The color seen looking along `direction`, which must be normalized.
+    pub fn color(&self, direction: Vec3) -> Rgb {
+        match self {
+            Background::Constant(rgb) => *rgb,
+            Background::VerticalGradient { bottom, top } => {
+                let t = (direction.y() + 1.0) / 2.0;
+                *bottom * (1.0 - t) + *top * t
+            }
+            Background::Environment(environment_map) => environment_map.color(direction),
+            Background::CubeMap(cube_map) => cube_map.color(direction),
+            Background::Sky(sky) => sky.color(direction),
+            Background::None => Rgb::black(),
+        }
+    }
+}
+
+/// A panorama in the equirectangular layout most HDR environment maps use:
+/// longitude runs across the image and latitude runs down it, with straight
+/// up along the top edge. Looking down -z sees the middle of the image.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnvironmentMap {
+    texture: Texture,
+}
+
+impl EnvironmentMap {
+    /// Wraps around horizontally, whatever `texture`'s own wrap modes are.
+    pub fn new(texture: Texture) -> EnvironmentMap {
+        let mut texture = texture;
+        texture.wrap_u = TextureWrap::Repeat;
+        texture.wrap_v = TextureWrap::Clamp;
+        EnvironmentMap { texture }
+    }
+
+    pub fn from_canvas(canvas: &Canvas) -> EnvironmentMap {
+        EnvironmentMap::new(Texture::from_canvas(canvas))
+    }
+
+    /// Loads any image `Texture::load` supports. HDR formats like Radiance
+    /// `.hdr` and OpenEXR keep their full range.
+    pub fn load(path: &Utf8Path) -> ImageResult<EnvironmentMap> {
+        Ok(EnvironmentMap::new(Texture::load(path)?))
+    }
+
+    pub fn color(&self, direction: Vec3) -> Rgb {
+        let u = 0.5 + direction.x().atan2(-direction.z()) / TAU;
+        let v = 1.0 - direction.y().clamp(-1.0, 1.0).acos() / PI;
+        self.texture.color_at_uv(u, v)
+    }
+}
+
+/// An analytic daylight sky, from Preetham, Shirley and Smits' "A Practical
+/// Analytic Model for Daylight". Directions below the horizon see the sky
+/// on the horizon straight above them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PreethamSky {
+    sun_direction: Vec3,
+    /// How hazy the air is. 2.0 is a very clear day and 10.0 is hazy.
+    pub turbidity: f32,
+    /// Scales the sky's luminance, which is in thousands of candelas per
+    /// square meter, into a displayable range.
+    pub exposure: f32,
+}
+
+impl PreethamSky {
+    /// `sun_direction` points from the ground towards the sun.
+    pub fn new(sun_direction: Vec3, turbidity: f32) -> PreethamSky {
+        PreethamSky {
+            sun_direction: sun_direction.normalize(),
+            turbidity: turbidity.max(1.0),
+            exposure: 0.05,
+        }
+    }
+
+    pub fn with_exposure(mut self, exposure: f32) -> PreethamSky {
+        self.exposure = exposure;
+        self
+    }
+
+    pub fn sun_direction(&self) -> Vec3 {
+        self.sun_direction
+    }
+
+    pub fn color(&self, direction: Vec3) -> Rgb {
+        let t = self.turbidity;
+        // Keep the sun just above the horizon, where the model still holds.
+        let sun_theta = self.sun_direction.y().clamp(0.01, 1.0).acos();
+        let direction = if direction.y() < 0.0 {
+            let horizontal = vec3(direction.x(), 0.0, direction.z());
+            if horizontal.length() > 0.0 {
+                horizontal.normalize()
+            } else {
+                vec3(0.0, 0.0, -1.0)
+            }
+        } else {
+            direction
+        };
+        let cos_theta = direction.y().max(0.001);
+        let cos_gamma = direction.dot(self.sun_direction).clamp(-1.0, 1.0);
+
+        let luminance_coefficients = [
+            0.1787 * t - 1.4630,
+            -0.3554 * t + 0.4275,
+            -0.0227 * t + 5.3251,
+            0.1206 * t - 2.5771,
+            -0.0670 * t + 0.3703,
+        ];
+        let x_coefficients = [
+            -0.0193 * t - 0.2592,
+            -0.0665 * t + 0.0008,
+            -0.0004 * t + 0.2125,
+            -0.0641 * t - 0.8989,
+            -0.0033 * t + 0.0452,
+        ];
+        let y_coefficients = [
+            -0.0167 * t - 0.2608,
+            -0.0950 * t + 0.0092,
+            -0.0079 * t + 0.2102,
+            -0.0441 * t - 1.6537,
+            -0.0109 * t + 0.0529,
+        ];
+
+        let chi = (4.0 / 9.0 - t / 120.0) * (PI - 2.0 * sun_theta);
+        let zenith_luminance = (4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192;
+        let theta = [sun_theta.powi(3), sun_theta.powi(2), sun_theta, 1.0];
+        let zenith_chromaticity = |rows: [[f32; 4]; 3]| {
+            let row = |coefficients: [f32; 4]| -> f32 {
+                coefficients.iter().zip(theta).map(|(c, th)| c * th).sum()
+            };
+            t * t * row(rows[0]) + t * row(rows[1]) + row(rows[2])
+        };
+        let zenith_x = zenith_chromaticity([
+            [0.00166, -0.00375, 0.00209, 0.0],
+            [-0.02903, 0.06377, -0.03202, 0.00394],
+            [0.11693, -0.21196, 0.06052, 0.25886],
+        ]);
+        let zenith_y = zenith_chromaticity([
+            [0.00275, -0.00610, 0.00317, 0.0],
+            [-0.04214, 0.08970, -0.04153, 0.00516],
+            [0.15346, -0.26756, 0.06670, 0.26688],
+        ]);
+
+        // Each value is its zenith value, scaled by how the sky's distribution
+        // here compares to its distribution at the zenith.
+        let relative = |coefficients: [f32; 5]| {
+            let distribution = perez(coefficients, cos_theta, cos_gamma);
+            let at_zenith = perez(coefficients, 1.0, sun_theta.cos());
+            distribution / at_zenith
+        };
+        let luminance = zenith_luminance * relative(luminance_coefficients) * self.exposure;
+        let x = zenith_x * relative(x_coefficients);
+        let y = zenith_y * relative(y_coefficients);
+
+        // xyY to XYZ to linear sRGB.
+        let big_x = x / y * luminance;
+        let big_z = (1.0 - x - y) / y * luminance;
+        let big_y = luminance;
+        Rgb::new(
+            (3.2406 * big_x - 1.5372 * big_y - 0.4986 * big_z).max(0.0),
+            (-0.9689 * big_x + 1.8758 * big_y + 0.0415 * big_z).max(0.0),
+            (0.0557 * big_x - 0.2040 * big_y + 1.0570 * big_z).max(0.0),
+        )
+    }
+}
+
+/// The Perez sky distribution, for a view direction `theta` from the zenith
+/// and `gamma` from the sun.
+fn perez([a, b, c, d, e]: [f32; 5], cos_theta: f32, cos_gamma: f32) -> f32 {
+    let gamma = cos_gamma.acos();
+    (1.0 + a * (b / cos_theta).exp()) * (1.0 + c * (d * gamma).exp() + e * cos_gamma * cos_gamma)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::pt2;
+    use super::*;
+
+    #[test]
+    fn the_default_background_is_black() {
+        let background = Background::default();
+        assert_eq!(background.color(vec3(0.0, 1.0, 0.0)), Rgb::black());
+    }
+
+    #[test]
+    fn a_constant_background_is_the_same_in_every_direction() {
+        let rgb = Rgb::new(0.2, 0.4, 0.6);
+        let background = Background::Constant(rgb);
+        assert_eq!(background.color(vec3(0.0, 1.0, 0.0)), rgb);
+        assert_eq!(background.color(vec3(0.6, 0.0, -0.8)), rgb);
+    }
+
+    #[test]
+    fn a_vertical_gradient_blends_from_bottom_to_top() {
+        let background = Background::VerticalGradient {
+            bottom: Rgb::black(),
+            top: Rgb::new(1.0, 1.0, 1.0),
+        };
+        assert_eq!(background.color(vec3(0.0, -1.0, 0.0)), Rgb::black());
+        assert_eq!(
+            background.color(vec3(1.0, 0.0, 0.0)),
+            Rgb::new(0.5, 0.5, 0.5)
+        );
+        assert_eq!(
+            background.color(vec3(0.0, 1.0, 0.0)),
+            Rgb::new(1.0, 1.0, 1.0)
+        );
+    }
+
+    #[test]
+    fn an_environment_map_is_looked_up_by_direction() {
+        // Red across the top row, green through the middle and blue across
+        // the bottom.
+        let mut canvas: Canvas = Canvas::new(8, 3);
+        for xy in canvas.iter_points() {
+            let rgb = match xy.y() as u32 {
+                0 => Rgb::new(1.0, 0.0, 0.0),
+                1 => Rgb::new(0.0, 1.0, 0.0),
+                _ => Rgb::new(0.0, 0.0, 1.0),
+            };
+            canvas.set_pixel_rgb(xy, rgb);
+        }
+        canvas.set_pixel_rgb(pt2(4.0, 1.0), Rgb::new(1.0, 1.0, 1.0));
+        let environment_map = EnvironmentMap::from_canvas(&canvas);
+
+        assert_eq!(
+            environment_map.color(vec3(0.0, 1.0, 0.0)),
+            Rgb::new(1.0, 0.0, 0.0)
+        );
+        assert_eq!(
+            environment_map.color(vec3(0.0, -1.0, 0.0)),
+            Rgb::new(0.0, 0.0, 1.0)
+        );
+        // Looking down -z lands on the corner between columns 3 and 4.
+        let ahead = environment_map.color(vec3(0.0, 0.0, -1.0));
+        assert_eq!(ahead, Rgb::new(0.5, 1.0, 0.5));
+        // Looking down +z wraps around the left and right edges.
+        let behind = environment_map.color(vec3(0.0, 0.0, 1.0));
+        assert_eq!(behind, Rgb::new(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn the_sky_is_brightest_towards_the_sun() {
+        let sun_direction = vec3(1.0, 0.5, 0.0).normalize();
+        let sky = PreethamSky::new(sun_direction, 3.0);
+        let luminance = |rgb: Rgb| 0.2126 * rgb.red() + 0.7152 * rgb.green() + 0.0722 * rgb.blue();
+        let near_sun = luminance(sky.color(vec3(0.9, 0.6, 0.0).normalize()));
+        let away_from_sun = luminance(sky.color(vec3(-0.9, 0.6, 0.0).normalize()));
+        assert!(near_sun > away_from_sun, "{near_sun} {away_from_sun}");
+    }
+
+    #[test]
+    fn a_clear_sky_is_blue_overhead() {
+        let sky = PreethamSky::new(vec3(1.0, 0.3, 0.0), 2.5);
+        let zenith = sky.color(vec3(0.0, 1.0, 0.0));
+        assert!(zenith.blue() > zenith.red(), "{zenith:?}");
+        for component in zenith.components() {
+            assert!(component.is_finite() && component > 0.0, "{zenith:?}");
+        }
+        // Below the horizon looks like just above it.
+        let below = sky.color(vec3(0.0, -1.0, 1.0).normalize());
+        let horizon = sky.color(vec3(0.0, 0.0, 1.0));
+        assert_eq!(below, horizon);
+    }
+}
@@ -0,0 +1,238 @@
+use super::{Noise, Point3, Texture, Transform, UvMapping, Vec3};
+
+/// How far apart height samples are taken, in object space.
+const DELTA: f32 = 0.001;
+
+/// Where a bump map's perturbation comes from, in its own bump map space.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BumpKind {
+    /// Tilts the normal by up to `strength` in a direction given by `noise`.
+    /// Good for ripples on water and rough, hammered surfaces.
+    Noise { noise: Noise, strength: f32 },
+    /// Treats the brightness of an image wrapped around the shape as height,
+    /// `strength` units high at white, and tilts the normal down its slope.
+    HeightMap {
+        texture: Texture,
+        mapping: UvMapping,
+        strength: f32,
+    },
+    /// Replaces the normal with one stored in an image wrapped around the
+    /// shape, in the usual tangent space encoding: red along `u`, green along
+    /// `v` and blue straight out of the surface, each from -1.0 at 0.0 to 1.0
+    /// at 1.0.
+    NormalMap {
+        texture: Texture,
+        mapping: UvMapping,
+    },
+}
+
+/// Perturbs a shape's normals to fake detail without extra geometry. Bump
+/// maps are transformed relative to the shape they're on, like patterns.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BumpMap {
+    pub kind: BumpKind,
+    transform: Transform,
+    inverse_transform: Transform,
+}
+
+impl BumpMap {
+    pub fn new(kind: BumpKind) -> BumpMap {
+        BumpMap {
+            kind,
+            transform: Transform::identity(),
+            inverse_transform: Transform::identity(),
+        }
+    }
+
+    pub fn noise(noise: Noise, strength: f32) -> BumpMap {
+        BumpMap::new(BumpKind::Noise { noise, strength })
+    }
+
+    pub fn height_map(texture: Texture, mapping: UvMapping, strength: f32) -> BumpMap {
+        BumpMap::new(BumpKind::HeightMap {
+            texture,
+            mapping,
+            strength,
+        })
+    }
+
+    pub fn normal_map(texture: Texture, mapping: UvMapping) -> BumpMap {
+        BumpMap::new(BumpKind::NormalMap { texture, mapping })
+    }
+
+    pub fn with_transform(mut self, transform: Transform) -> BumpMap {
+        self.set_transform(transform);
+        self
+    }
+
+    pub fn transform(&self) -> Transform {
+        self.transform
+    }
+
+    pub fn set_transform(&mut self, transform: Transform) {
+        self.transform = transform;
+        self.inverse_transform = transform.inverse();
+    }
+
+    /// `normal` at `object_point`, both in object space, tilted by the bump
+    /// map. Always normalized.
+    ///
+    /// The tangent frame stays in object space, and only the points the map
+    /// is looked up at are moved into bump map space, so slopes and
+    /// directions come out in object space however the map is transformed.
+    pub fn perturb(&self, object_point: Point3, normal: Vec3) -> Vec3 {
+        let normal = normal.normalize();
+        let bump_point = |object_point: Point3| self.inverse_transform * object_point;
+        let (tangent, bitangent) = normal.perpendiculars();
+        let perturbed = match &self.kind {
+            BumpKind::Noise { noise, strength } => {
+                normal + noise.vector(bump_point(object_point)) * *strength
+            }
+            BumpKind::HeightMap {
+                texture,
+                mapping,
+                strength,
+            } => {
+                let height = |object_point: Point3| {
+                    let (u, v) = mapping.uv(bump_point(object_point));
+                    let [red, green, blue] = texture.color_at_uv(u, v).components();
+                    (red + green + blue) / 3.0 * strength
+                };
+                let here = height(object_point);
+                let along_tangent = (height(object_point + tangent * DELTA) - here) / DELTA;
+                let along_bitangent = (height(object_point + bitangent * DELTA) - here) / DELTA;
+                normal - tangent * along_tangent - bitangent * along_bitangent
+            }
+            BumpKind::NormalMap { texture, mapping } => {
+                let uv = |object_point: Point3| mapping.uv(bump_point(object_point));
+                let (u, v) = uv(object_point);
+                let [x, y, z] = texture
+                    .color_at_uv(u, v)
+                    .components()
+                    .map(|c| c * 2.0 - 1.0);
+                let (u_direction, v_direction) =
+                    uv_directions(uv, object_point, tangent, bitangent);
+                u_direction * x + v_direction * y + normal * z
+            }
+        };
+        perturbed.normalize()
+    }
+}
+
+/// Unit vectors in the plane of `tangent` and `bitangent` along which `u`
+/// and `v` increase, found by nudging `point` along each and seeing how `uv`
+/// changes.
+fn uv_directions(
+    uv: impl Fn(Point3) -> (f32, f32),
+    point: Point3,
+    tangent: Vec3,
+    bitangent: Vec3,
+) -> (Vec3, Vec3) {
+    let (u, v) = uv(point);
+    // Differences across the seam where u or v wraps around from 1.0 to 0.0
+    // are really small steps the other way.
+    let difference = |a: f32, b: f32| (b - a + 0.5).rem_euclid(1.0) - 0.5;
+    let (u1, v1) = uv(point + tangent * DELTA);
+    let (u2, v2) = uv(point + bitangent * DELTA);
+    let (du1, dv1) = (difference(u, u1), difference(v, v1));
+    let (du2, dv2) = (difference(u, u2), difference(v, v2));
+    let determinant = du1 * dv2 - du2 * dv1;
+    if determinant.abs() < f32::EPSILON {
+        return (tangent, bitangent);
+    }
+    let u_direction = (tangent * dv2 - bitangent * dv1) * determinant.signum();
+    let v_direction = (bitangent * du1 - tangent * du2) * determinant.signum();
+    (u_direction.normalize(), v_direction.normalize())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{pt2, pt3, vec3, Rgb};
+    use super::*;
+    use crate::library::canvas::Canvas;
+    use std::f32::consts::FRAC_PI_2;
+
+    fn up() -> Vec3 {
+        vec3(0.0, 1.0, 0.0)
+    }
+
+    #[test]
+    fn noise_tilts_the_normal() {
+        let bump_map = BumpMap::noise(Noise::new(4), 0.5);
+        let points = (0..20).map(|i| pt3(i as f32 * 0.37, 0.0, i as f32 * 0.23));
+        let normals: Vec<Vec3> = points.map(|point| bump_map.perturb(point, up())).collect();
+        for normal in &normals {
+            assert!((normal.length() - 1.0).abs() < 1e-5);
+            assert!(normal.y() > 0.0);
+        }
+        assert!(normals.iter().any(|&normal| normal != up()));
+    }
+
+    #[test]
+    fn a_bump_map_with_no_strength_leaves_the_normal_alone() {
+        let bump_map = BumpMap::noise(Noise::new(4), 0.0);
+        assert_eq!(bump_map.perturb(pt3(0.3, 0.0, 0.7), up()), up());
+    }
+
+    #[test]
+    fn a_flat_height_map_leaves_the_normal_alone() {
+        let texture = Texture::solid(Rgb::new(0.5, 0.5, 0.5));
+        let bump_map = BumpMap::height_map(texture, UvMapping::Planar, 1.0);
+        assert_eq!(bump_map.perturb(pt3(0.3, 0.0, 0.7), up()), up());
+    }
+
+    #[test]
+    fn a_height_map_tilts_the_normal_down_its_slope() {
+        // Black on the left, rising to white on the right.
+        let mut canvas: Canvas = Canvas::new(2, 1);
+        canvas.set_pixel_rgb(pt2(1.0, 0.0), Rgb::new(1.0, 1.0, 1.0));
+        let bump_map = BumpMap::height_map(Texture::from_canvas(&canvas), UvMapping::Planar, 0.1);
+        let normal = bump_map.perturb(pt3(0.5, 0.0, 0.5), up());
+        assert!(normal.x() < -0.05, "{normal:?}");
+        assert!(normal.z().abs() < 1e-4, "{normal:?}");
+    }
+
+    #[test]
+    fn a_flat_normal_map_leaves_the_normal_alone() {
+        let texture = Texture::solid(Rgb::new(0.5, 0.5, 1.0));
+        let bump_map = BumpMap::normal_map(texture, UvMapping::Planar);
+        assert_eq!(bump_map.perturb(pt3(0.3, 0.0, 0.7), up()), up());
+    }
+
+    #[test]
+    fn a_normal_map_is_in_tangent_space() {
+        // Planar mapping runs u along x and v along z.
+        let along_u = Texture::solid(Rgb::new(1.0, 0.5, 0.5));
+        let bump_map = BumpMap::normal_map(along_u, UvMapping::Planar);
+        assert_eq!(
+            bump_map.perturb(pt3(0.3, 0.0, 0.7), up()),
+            vec3(1.0, 0.0, 0.0)
+        );
+        let along_v = Texture::solid(Rgb::new(0.5, 1.0, 0.5));
+        let bump_map = BumpMap::normal_map(along_v, UvMapping::Planar);
+        assert_eq!(
+            bump_map.perturb(pt3(0.3, 0.0, 0.7), up()),
+            vec3(0.0, 0.0, 1.0)
+        );
+    }
+
+    #[test]
+    fn bump_maps_are_looked_up_in_their_own_space() {
+        // Black on the left, rising to white on the right, turned so the slope
+        // runs along z instead of x.
+        let mut canvas: Canvas = Canvas::new(2, 1);
+        canvas.set_pixel_rgb(pt2(1.0, 0.0), Rgb::new(1.0, 1.0, 1.0));
+        let bump_map = BumpMap::height_map(Texture::from_canvas(&canvas), UvMapping::Planar, 0.1)
+            .with_transform(Transform::rotation_y(-FRAC_PI_2));
+        let normal = bump_map.perturb(pt3(0.5, 0.0, 0.5), up());
+        assert!(normal.x().abs() < 1e-4, "{normal:?}");
+        assert!(normal.z().abs() > 0.05, "{normal:?}");
+
+        let along_u = Texture::solid(Rgb::new(1.0, 0.5, 0.5));
+        let bump_map = BumpMap::normal_map(along_u, UvMapping::Planar)
+            .with_transform(Transform::rotation_y(-FRAC_PI_2));
+        let normal = bump_map.perturb(pt3(0.3, 0.0, 0.7), up());
+        assert!(normal.x().abs() < 1e-4, "{normal:?}");
+        assert!((normal.z().abs() - 1.0).abs() < 1e-4, "{normal:?}");
+    }
+}
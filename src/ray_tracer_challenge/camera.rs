@@ -0,0 +1,357 @@
+use crate::library::canvas::Canvas;
+use crate::library::tile_renderer::TileRenderer;
+
+use rand::Rng;
+
+use super::{pt2, pt3, rng_for_pixel, Lens, Point2, Ray, Rgb, Sampling, Transform};
+
+/// Maps the pixels of a `hsize` by `vsize` canvas onto a view of the world.
+///
+/// The camera sits at the origin looking down the negative z axis, one unit
+/// away from the canvas. Use `set_transform` with `Transform::view_transform`
+/// to move it.
+#[derive(Debug, Clone)]
+pub struct Camera {
+    pub sampling: Sampling,
+    /// A pinhole by default. Give it an aperture for depth of field.
+    pub lens: Lens,
+    /// When the shutter opens and closes, in the same time as `Motion::at`
+    /// and `Ray::time`. Each ray is cast at a random time in between, which
+    /// blurs moving shapes. Both are 0.0 by default, which freezes motion.
+    pub shutter_open: f32,
+    pub shutter_close: f32,
+    hsize: u32,
+    vsize: u32,
+    field_of_view: f32,
+    transform: Transform,
+    inverse_transform: Transform,
+    half_width: f32,
+    half_height: f32,
+    pixel_size: f32,
+}
+
+impl Camera {
+    pub fn new(hsize: u32, vsize: u32, field_of_view: f32) -> Camera {
+        let half_view = (field_of_view / 2.0).tan();
+        let aspect = hsize as f32 / vsize as f32;
+        let (half_width, half_height) = if aspect >= 1.0 {
+            (half_view, half_view / aspect)
+        } else {
+            (half_view * aspect, half_view)
+        };
+        Camera {
+            hsize,
+            vsize,
+            field_of_view,
+            sampling: Sampling::single(),
+            lens: Lens::pinhole(),
+            shutter_open: 0.0,
+            shutter_close: 0.0,
+            transform: Transform::identity(),
+            inverse_transform: Transform::identity(),
+            half_width,
+            half_height,
+            pixel_size: half_width * 2.0 / hsize as f32,
+        }
+    }
+
+    pub fn hsize(&self) -> u32 {
+        self.hsize
+    }
+
+    pub fn vsize(&self) -> u32 {
+        self.vsize
+    }
+
+    pub fn field_of_view(&self) -> f32 {
+        self.field_of_view
+    }
+
+    pub fn transform(&self) -> Transform {
+        self.transform
+    }
+
+    pub fn set_transform(&mut self, transform: Transform) {
+        self.transform = transform;
+        self.inverse_transform = transform.inverse();
+    }
+
+    /// The width of a pixel, one unit in front of the camera.
+    pub fn pixel_size(&self) -> f32 {
+        self.pixel_size
+    }
+
+    /// A ray through the center of the pixel.
+    pub fn ray_for_pixel(&self, px: u32, py: u32) -> Ray {
+        self.ray_for_pixel_offset(px, py, pt2(0.5, 0.5))
+    }
+
+    /// A ray through `offset` within the pixel, where `(0.0, 0.0)` is the
+    /// pixel's top left corner and `(1.0, 1.0)` is its bottom right.
+    pub fn ray_for_pixel_offset(&self, px: u32, py: u32, offset: Point2) -> Ray {
+        let (world_x, world_y) = self.canvas_point(px, py, offset);
+        let pixel = self.inverse_transform * pt3(world_x, world_y, -1.0);
+        let origin = self.inverse_transform * pt3(0.0, 0.0, 0.0);
+        Ray::new(origin, (pixel - origin).normalize())
+    }
+
+    /// Like `ray_for_pixel_offset`, but starting from a point on the lens
+    /// instead of its center. `lens_sample` is a point in the unit square
+    /// that's mapped onto the aperture. All rays through the same pixel
+    /// offset meet again at the focal distance, so only things that far away
+    /// are sharp.
+    pub fn ray_for_pixel_through_lens(
+        &self,
+        px: u32,
+        py: u32,
+        offset: Point2,
+        lens_sample: Point2,
+    ) -> Ray {
+        if self.lens.is_pinhole() {
+            return self.ray_for_pixel_offset(px, py, offset);
+        }
+        let (world_x, world_y) = self.canvas_point(px, py, offset);
+        let focal_distance = self.lens.focal_distance;
+        let focal_point = pt3(
+            world_x * focal_distance,
+            world_y * focal_distance,
+            -focal_distance,
+        );
+        let lens_point = self.lens.point_on_lens(lens_sample);
+
+        let focal_point = self.inverse_transform * focal_point;
+        let origin = self.inverse_transform * pt3(lens_point.x(), lens_point.y(), 0.0);
+        Ray::new(origin, (focal_point - origin).normalize())
+    }
+
+    /// The time `fraction` of the way from the shutter opening to closing.
+    pub fn shutter_time(&self, fraction: f32) -> f32 {
+        self.shutter_open + fraction * (self.shutter_close - self.shutter_open)
+    }
+
+    /// Where the pixel offset lands on the canvas, one unit in front of the
+    /// camera.
+    fn canvas_point(&self, px: u32, py: u32, offset: Point2) -> (f32, f32) {
+        let x_offset = (px as f32 + offset.x()) * self.pixel_size;
+        let y_offset = (py as f32 + offset.y()) * self.pixel_size;
+
+        // Canvas x runs right and y runs down, but the camera looks down -z,
+        // so +x is to the left.
+        (self.half_width - x_offset, self.half_height - y_offset)
+    }
+
+    /// Traces every sample that `sampling` places in the pixel and combines
+    /// them with its reconstruction filter. Each sample is traced through
+    /// several random points on the lens if it has an aperture, and at a
+    /// random time while the shutter is open.
+    pub fn render_pixel(&self, px: u32, py: u32, color_for_ray: impl Fn(Ray) -> Rgb) -> Rgb {
+        let samples = self.sampling.samples_for_pixel(px, py);
+        let mut rng = rng_for_pixel(px, py, 1);
+        let mut total = Rgb::black();
+        let mut total_weight = 0.0;
+        for sample in &samples {
+            for _ in 0..self.lens.samples_per_pixel_sample() {
+                let lens_sample = pt2(rng.gen(), rng.gen());
+                let time = self.shutter_time(rng.gen());
+                let ray = self
+                    .ray_for_pixel_through_lens(px, py, sample.offset, lens_sample)
+                    .with_time(time);
+                total = total + color_for_ray(ray) * sample.weight;
+                total_weight += sample.weight;
+            }
+        }
+        if total_weight > 0.0 {
+            total * (1.0 / total_weight)
+        } else {
+            Rgb::black()
+        }
+    }
+
+    /// Renders every pixel on a pool of worker threads.
+    pub fn render(&self, color_for_ray: impl Fn(Ray) -> Rgb + Sync) -> Canvas {
+        let mut canvas = Canvas::new(self.hsize, self.vsize);
+        TileRenderer::new().render(&mut canvas, |xy| {
+            self.render_pixel(xy.x() as u32, xy.y() as u32, &color_for_ray)
+        });
+        canvas
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{vec3, ApertureShape, ReconstructionFilter, SamplePattern};
+    use super::*;
+    use crate::extensions::F32Extension;
+    use std::f32::consts::{FRAC_1_SQRT_2, PI};
+
+    #[test]
+    fn constructing_a_camera() {
+        let camera = Camera::new(160, 120, PI / 2.0);
+        assert_eq!(camera.hsize(), 160);
+        assert_eq!(camera.vsize(), 120);
+        assert_eq!(camera.field_of_view(), PI / 2.0);
+        assert_eq!(camera.transform(), Transform::identity());
+    }
+
+    #[test]
+    fn the_pixel_size_for_a_horizontal_canvas() {
+        let camera = Camera::new(200, 125, PI / 2.0);
+        assert!(camera.pixel_size().almost_eq(0.01));
+    }
+
+    #[test]
+    fn the_pixel_size_for_a_vertical_canvas() {
+        let camera = Camera::new(125, 200, PI / 2.0);
+        assert!(camera.pixel_size().almost_eq(0.01));
+    }
+
+    #[test]
+    fn constructing_a_ray_through_the_center_of_the_canvas() {
+        let camera = Camera::new(201, 101, PI / 2.0);
+        let ray = camera.ray_for_pixel(100, 50);
+        assert_eq!(ray.origin, pt3(0.0, 0.0, 0.0));
+        assert_eq!(ray.direction, vec3(0.0, 0.0, -1.0));
+    }
+
+    #[test]
+    fn constructing_a_ray_through_a_corner_of_the_canvas() {
+        let camera = Camera::new(201, 101, PI / 2.0);
+        let ray = camera.ray_for_pixel(0, 0);
+        assert_eq!(ray.origin, pt3(0.0, 0.0, 0.0));
+        let expected = vec3(0.66519, 0.33259, -0.66851);
+        assert!((ray.direction - expected).length() < 0.0001);
+    }
+
+    #[test]
+    fn constructing_a_ray_when_the_camera_is_transformed() {
+        let mut camera = Camera::new(201, 101, PI / 2.0);
+        camera.set_transform(
+            Transform::translation(0.0, -2.0, 5.0).then(Transform::rotation_y(PI / 4.0)),
+        );
+        let ray = camera.ray_for_pixel(100, 50);
+        assert_eq!(ray.origin, pt3(0.0, 2.0, -5.0));
+        assert_eq!(ray.direction, vec3(FRAC_1_SQRT_2, 0.0, -FRAC_1_SQRT_2));
+    }
+
+    #[test]
+    fn a_single_sample_renders_the_center_ray() {
+        let camera = Camera::new(11, 11, PI / 2.0);
+        let center = camera.ray_for_pixel(3, 7);
+        let rgb = camera.render_pixel(3, 7, |ray| {
+            assert_eq!(ray, center);
+            Rgb::new(0.25, 0.5, 0.75)
+        });
+        assert_eq!(rgb, Rgb::new(0.25, 0.5, 0.75));
+    }
+
+    #[test]
+    fn supersampling_blends_colors_across_an_edge() {
+        // The world is white wherever x < 0, which splits the middle pixel of
+        // an 11 pixel wide canvas in half. One sample can only pick a side.
+        let half_white = |ray: Ray| {
+            if ray.direction.x() < 0.0 {
+                Rgb::new(1.0, 1.0, 1.0)
+            } else {
+                Rgb::black()
+            }
+        };
+        let mut camera = Camera::new(11, 11, PI / 2.0);
+
+        let aliased = camera.render_pixel(5, 5, half_white);
+        assert!(aliased == Rgb::black() || aliased == Rgb::new(1.0, 1.0, 1.0));
+
+        for pattern in [
+            SamplePattern::Regular,
+            SamplePattern::Jittered,
+            SamplePattern::Halton,
+            SamplePattern::Sobol,
+        ] {
+            camera.sampling = Sampling::new(pattern, 16, ReconstructionFilter::Box);
+            let blended = camera.render_pixel(5, 5, half_white);
+            assert!(
+                blended.red() > 0.3 && blended.red() < 0.7,
+                "{pattern:?}: {blended:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn rays_through_the_lens_meet_at_the_focal_distance() {
+        let mut camera = Camera::new(11, 11, PI / 2.0);
+        camera.set_transform(Transform::translation(0.0, 0.0, -2.0));
+        camera.lens = Lens::new(0.5, 4.0);
+        let offset = pt2(0.5, 0.5);
+        let center = camera.ray_for_pixel_offset(2, 3, offset);
+        let focal_t = 4.0 / -center.direction.z();
+        let focal_point = center.position(focal_t);
+
+        for lens_sample in [pt2(0.0, 0.0), pt2(1.0, 0.5), pt2(0.2, 0.9)] {
+            let ray = camera.ray_for_pixel_through_lens(2, 3, offset, lens_sample);
+            assert_ne!(ray.origin, center.origin);
+            let t = 4.0 / -ray.direction.z();
+            assert_eq!(ray.position(t), focal_point);
+        }
+    }
+
+    #[test]
+    fn a_pinhole_lens_traces_through_its_center() {
+        let camera = Camera::new(11, 11, PI / 2.0);
+        let center = camera.ray_for_pixel(3, 7);
+        let ray = camera.ray_for_pixel_through_lens(3, 7, pt2(0.5, 0.5), pt2(0.9, 0.1));
+        assert_eq!(ray, center);
+    }
+
+    #[test]
+    fn things_away_from_the_focal_distance_are_blurred() {
+        // A wall 5 units away is white on the left half and black on the right.
+        let wall = |ray: Ray| {
+            let t = 5.0 / -ray.direction.z();
+            if ray.position(t).x() < 0.0 {
+                Rgb::new(1.0, 1.0, 1.0)
+            } else {
+                Rgb::black()
+            }
+        };
+        let mut camera = Camera::new(21, 21, PI / 2.0);
+        // A pixel just left of the edge, which is white without blur.
+        let (px, py) = (11, 10);
+        assert_eq!(camera.render_pixel(px, py, wall), Rgb::new(1.0, 1.0, 1.0));
+
+        for aperture_shape in [ApertureShape::Circle, ApertureShape::Polygon { sides: 6 }] {
+            camera.lens = Lens::new(1.0, 5.0).with_aperture_shape(aperture_shape);
+            assert_eq!(camera.render_pixel(px, py, wall), Rgb::new(1.0, 1.0, 1.0));
+
+            camera.lens = Lens::new(1.0, 1.0)
+                .with_aperture_shape(aperture_shape)
+                .with_num_samples(64);
+            let blurred = camera.render_pixel(px, py, wall);
+            assert!(
+                blurred.red() > 0.1 && blurred.red() < 0.9,
+                "{aperture_shape:?}: {blurred:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn rays_are_cast_while_the_shutter_is_open() {
+        let mut camera = Camera::new(11, 11, PI / 2.0);
+        camera.sampling = Sampling::new(SamplePattern::Jittered, 16, ReconstructionFilter::Box);
+        let times = std::sync::Mutex::new(vec![]);
+        camera.render_pixel(5, 5, |ray| {
+            times.lock().unwrap().push(ray.time);
+            Rgb::black()
+        });
+        assert!(times.lock().unwrap().iter().all(|&time| time == 0.0));
+
+        camera.shutter_open = 0.25;
+        camera.shutter_close = 0.75;
+        let times = std::sync::Mutex::new(vec![]);
+        camera.render_pixel(5, 5, |ray| {
+            times.lock().unwrap().push(ray.time);
+            Rgb::black()
+        });
+        let times = times.into_inner().unwrap();
+        assert!(times.iter().all(|&time| (0.25..=0.75).contains(&time)));
+        assert!(times.iter().any(|&time| time != times[0]));
+    }
+}
@@ -0,0 +1,176 @@
+use camino::Utf8Path;
+use image::ImageResult;
+
+use super::{pt3, Point3, Rgb, Texture, UvPattern, Vec3};
+
+/// One face of an axis-aligned cube, named as seen from outside the cube
+/// looking down -z.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CubeFace {
+    Left,
+    Front,
+    Right,
+    Back,
+    Up,
+    Down,
+}
+
+impl CubeFace {
+    /// The face that `point` is closest to, judging by its largest
+    /// component. `point` needn't be on the cube.
+    pub fn from_point(point: Point3) -> CubeFace {
+        let (x, y, z) = (point.x(), point.y(), point.z());
+        let coord = x.abs().max(y.abs()).max(z.abs());
+        if coord == x {
+            CubeFace::Right
+        } else if coord == -x {
+            CubeFace::Left
+        } else if coord == y {
+            CubeFace::Up
+        } else if coord == -y {
+            CubeFace::Down
+        } else if coord == z {
+            CubeFace::Front
+        } else {
+            CubeFace::Back
+        }
+    }
+
+    /// Where `point`, on the surface of the cube from -1.0 to 1.0 on every
+    /// axis, falls on this face. `u` runs left to right and `v` bottom to
+    /// top, as seen from outside.
+    pub fn uv(self, point: Point3) -> (f32, f32) {
+        let (x, y, z) = (point.x(), point.y(), point.z());
+        let wrap = |value: f32| value.rem_euclid(2.0) / 2.0;
+        match self {
+            CubeFace::Front => (wrap(x + 1.0), wrap(y + 1.0)),
+            CubeFace::Back => (wrap(1.0 - x), wrap(y + 1.0)),
+            CubeFace::Left => (wrap(z + 1.0), wrap(y + 1.0)),
+            CubeFace::Right => (wrap(1.0 - z), wrap(y + 1.0)),
+            CubeFace::Up => (wrap(x + 1.0), wrap(1.0 - z)),
+            CubeFace::Down => (wrap(x + 1.0), wrap(z + 1.0)),
+        }
+    }
+}
+
+/// A different `UvPattern` on each face of a cube, usually six images. Works
+/// both as a skybox, looked up by direction, and as a pattern on a cube shape.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CubeMap {
+    faces: Box<[UvPattern; 6]>,
+}
+
+impl CubeMap {
+    /// Takes the faces in the same order as `CubeFace`: left, front, right,
+    /// back, up, down.
+    pub fn new(faces: [UvPattern; 6]) -> CubeMap {
+        CubeMap {
+            faces: Box::new(faces),
+        }
+    }
+
+    /// Loads each face from its own image, in the same order as `new`.
+    pub fn load(paths: [&Utf8Path; 6]) -> ImageResult<CubeMap> {
+        let [left, front, right, back, up, down] = paths;
+        Ok(CubeMap::new([
+            UvPattern::Image(Texture::load(left)?),
+            UvPattern::Image(Texture::load(front)?),
+            UvPattern::Image(Texture::load(right)?),
+            UvPattern::Image(Texture::load(back)?),
+            UvPattern::Image(Texture::load(up)?),
+            UvPattern::Image(Texture::load(down)?),
+        ]))
+    }
+
+    pub fn face(&self, face: CubeFace) -> &UvPattern {
+        &self.faces[face as usize]
+    }
+
+    /// The color on the surface of the cube from -1.0 to 1.0, at `point`.
+    pub fn color_at(&self, point: Point3) -> Rgb {
+        let face = CubeFace::from_point(point);
+        let (u, v) = face.uv(point);
+        self.face(face).color_at(u, v)
+    }
+
+    /// The color seen looking out from the center of the cube along
+    /// `direction`.
+    pub fn color(&self, direction: Vec3) -> Rgb {
+        let largest = direction
+            .x()
+            .abs()
+            .max(direction.y().abs())
+            .max(direction.z().abs());
+        if largest == 0.0 {
+            return Rgb::black();
+        }
+        let on_cube = direction / largest;
+        self.color_at(pt3(on_cube.x(), on_cube.y(), on_cube.z()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::vec3;
+    use super::*;
+
+    #[test]
+    fn identifying_the_face_of_a_cube_from_a_point() {
+        let cases = [
+            (pt3(-1.0, 0.5, -0.25), CubeFace::Left),
+            (pt3(1.1, -0.75, 0.8), CubeFace::Right),
+            (pt3(0.1, 0.6, 0.9), CubeFace::Front),
+            (pt3(-0.7, 0.0, -2.0), CubeFace::Back),
+            (pt3(0.5, 1.0, 0.9), CubeFace::Up),
+            (pt3(-0.2, -1.3, 1.1), CubeFace::Down),
+        ];
+        for (point, expected) in cases {
+            assert_eq!(CubeFace::from_point(point), expected, "{point:?}");
+        }
+    }
+
+    #[test]
+    fn uv_mapping_each_face_of_a_cube() {
+        let cases = [
+            (CubeFace::Front, pt3(-0.5, 0.5, 1.0), (0.25, 0.75)),
+            (CubeFace::Front, pt3(0.5, -0.5, 1.0), (0.75, 0.25)),
+            (CubeFace::Back, pt3(0.5, 0.5, -1.0), (0.25, 0.75)),
+            (CubeFace::Back, pt3(-0.5, -0.5, -1.0), (0.75, 0.25)),
+            (CubeFace::Left, pt3(-1.0, 0.5, -0.5), (0.25, 0.75)),
+            (CubeFace::Left, pt3(-1.0, -0.5, 0.5), (0.75, 0.25)),
+            (CubeFace::Right, pt3(1.0, 0.5, 0.5), (0.25, 0.75)),
+            (CubeFace::Right, pt3(1.0, -0.5, -0.5), (0.75, 0.25)),
+            (CubeFace::Up, pt3(-0.5, 1.0, -0.5), (0.25, 0.75)),
+            (CubeFace::Up, pt3(0.5, 1.0, 0.5), (0.75, 0.25)),
+            (CubeFace::Down, pt3(-0.5, -1.0, 0.5), (0.25, 0.75)),
+            (CubeFace::Down, pt3(0.5, -1.0, -0.5), (0.75, 0.25)),
+        ];
+        for (face, point, expected) in cases {
+            assert_eq!(face.uv(point), expected, "{face:?} {point:?}");
+        }
+    }
+
+    #[test]
+    fn a_cube_map_looks_up_the_face_a_direction_points_at() {
+        let colors = [
+            Rgb::new(1.0, 0.0, 0.0),
+            Rgb::new(0.0, 1.0, 0.0),
+            Rgb::new(0.0, 0.0, 1.0),
+            Rgb::new(1.0, 1.0, 0.0),
+            Rgb::new(0.0, 1.0, 1.0),
+            Rgb::new(1.0, 0.0, 1.0),
+        ];
+        let cube_map = CubeMap::new(colors.map(|rgb| UvPattern::Image(Texture::solid(rgb))));
+        let cases = [
+            (vec3(-3.0, 1.0, 0.5), colors[0]),
+            (vec3(0.2, -0.1, 0.5), colors[1]),
+            (vec3(1.0, 0.0, 0.0), colors[2]),
+            (vec3(0.0, 0.4, -0.5), colors[3]),
+            (vec3(0.0, 1.0, 0.0), colors[4]),
+            (vec3(0.7, -0.8, 0.1), colors[5]),
+        ];
+        for (direction, expected) in cases {
+            assert_eq!(cube_map.color(direction), expected, "{direction:?}");
+        }
+    }
+}
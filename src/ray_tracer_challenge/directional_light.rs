@@ -0,0 +1,72 @@
+use super::{Light, LightSample, Point3, Rgb, Vec3};
+
+/// A light infinitely far away whose rays all travel in the same direction,
+/// like the sun. Anything between a point and the light casts a shadow, no
+/// matter how far away it is.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DirectionalLight {
+    direction: Vec3,
+    pub intensity: Rgb,
+}
+
+impl DirectionalLight {
+    /// `direction` is the direction the light travels in, so a noon sun is
+    /// `vec3(0.0, -1.0, 0.0)`.
+    pub fn new(direction: Vec3, intensity: Rgb) -> DirectionalLight {
+        DirectionalLight {
+            direction: direction.normalize(),
+            intensity,
+        }
+    }
+
+    pub fn direction(&self) -> Vec3 {
+        self.direction
+    }
+}
+
+impl Light for DirectionalLight {
+    fn intensity(&self) -> Rgb {
+        self.intensity
+    }
+
+    fn samples(&self, _point: Point3) -> Vec<LightSample> {
+        vec![LightSample {
+            direction: -self.direction,
+            distance: f32::INFINITY,
+            weight: 1.0,
+        }]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{pt3, vec3, Shape, Transform, World};
+    use super::*;
+
+    fn sun() -> DirectionalLight {
+        DirectionalLight::new(vec3(0.0, -2.0, 0.0), Rgb::new(1.0, 1.0, 1.0))
+    }
+
+    #[test]
+    fn a_directional_light_is_sampled_against_its_direction() {
+        let samples = sun().samples(pt3(4.0, 5.0, 6.0));
+        assert_eq!(
+            samples,
+            vec![LightSample {
+                direction: vec3(0.0, 1.0, 0.0),
+                distance: f32::INFINITY,
+                weight: 1.0,
+            }]
+        );
+    }
+
+    #[test]
+    fn a_directional_light_is_blocked_by_objects_at_any_distance() {
+        let mut world = World::new();
+        let far_away = Shape::sphere().with_transform(Transform::translation(0.0, 1000.0, 0.0));
+        world.objects.push(far_away);
+        let light = sun();
+        assert_eq!(light.intensity_at(pt3(0.0, 0.0, 0.0), 0.0, &world), 0.0);
+        assert_eq!(light.intensity_at(pt3(5.0, 0.0, 0.0), 0.0, &world), 1.0);
+    }
+}
@@ -0,0 +1,167 @@
+use super::{Point3, Ray, Shape, Vec3};
+
+/// How far shading points are nudged off a surface before casting shadow
+/// rays, so floating point error doesn't make surfaces shadow themselves.
+pub const EPSILON: f32 = 0.001;
+
+/// Where a ray crosses a shape's surface, `t` units along the ray.
+#[derive(Debug, Clone, Copy)]
+pub struct Intersection<'a> {
+    pub t: f32,
+    pub object: &'a Shape,
+}
+
+impl<'a> Intersection<'a> {
+    pub fn new(t: f32, object: &'a Shape) -> Intersection<'a> {
+        Intersection { t, object }
+    }
+
+    /// Precomputes everything shading needs to know about the intersection.
+    pub fn prepare_computations(&self, ray: Ray) -> Computations<'a> {
+        let point = ray.position(self.t);
+        let eyev = -ray.direction;
+        let (mut geometric_normal, mut normalv) = self.object.normals_at_time(point, ray.time);
+        // A bump map only changes how the surface is shaded, so which side of
+        // it the eye is on, and which way to nudge `over_point`, come from the
+        // surface itself.
+        let inside = geometric_normal.dot(eyev) < 0.0;
+        if inside {
+            geometric_normal = -geometric_normal;
+            normalv = -normalv;
+        }
+        Computations {
+            t: self.t,
+            object: self.object,
+            point,
+            over_point: point + geometric_normal * EPSILON,
+            eyev,
+            normalv,
+            inside,
+            time: ray.time,
+        }
+    }
+}
+
+impl PartialEq for Intersection<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.t == other.t && std::ptr::eq(self.object, other.object)
+    }
+}
+
+/// The visible intersection: the one with the lowest non-negative `t`.
+pub fn hit<'a, 'b>(intersections: &'b [Intersection<'a>]) -> Option<&'b Intersection<'a>> {
+    intersections
+        .iter()
+        .filter(|intersection| intersection.t >= 0.0)
+        .min_by(|a, b| a.t.total_cmp(&b.t))
+}
+
+pub struct Computations<'a> {
+    pub t: f32,
+    pub object: &'a Shape,
+    pub point: Point3,
+    /// `point`, nudged off the surface along the normal. Use this for
+    /// anything that casts rays from the surface.
+    pub over_point: Point3,
+    pub eyev: Vec3,
+    /// Flipped to the same side of the surface as `eyev`, even if that means
+    /// pointing into the shape, then tilted by the material's bump map.
+    pub normalv: Vec3,
+    pub inside: bool,
+    /// The ray's time, which shadow rays should be cast at too.
+    pub time: f32,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{pt3, vec3, Transform};
+    use super::*;
+
+    #[test]
+    fn the_hit_when_all_intersections_have_positive_t() {
+        let sphere = Shape::sphere();
+        let i1 = Intersection::new(1.0, &sphere);
+        let i2 = Intersection::new(2.0, &sphere);
+        assert_eq!(hit(&[i2, i1]), Some(&i1));
+    }
+
+    #[test]
+    fn the_hit_when_some_intersections_have_negative_t() {
+        let sphere = Shape::sphere();
+        let i1 = Intersection::new(-1.0, &sphere);
+        let i2 = Intersection::new(1.0, &sphere);
+        assert_eq!(hit(&[i2, i1]), Some(&i2));
+    }
+
+    #[test]
+    fn the_hit_when_all_intersections_have_negative_t() {
+        let sphere = Shape::sphere();
+        let i1 = Intersection::new(-2.0, &sphere);
+        let i2 = Intersection::new(-1.0, &sphere);
+        assert_eq!(hit(&[i2, i1]), None);
+    }
+
+    #[test]
+    fn the_hit_is_always_the_lowest_nonnegative_intersection() {
+        let sphere = Shape::sphere();
+        let i1 = Intersection::new(5.0, &sphere);
+        let i2 = Intersection::new(7.0, &sphere);
+        let i3 = Intersection::new(-3.0, &sphere);
+        let i4 = Intersection::new(2.0, &sphere);
+        assert_eq!(hit(&[i1, i2, i3, i4]), Some(&i4));
+    }
+
+    #[test]
+    fn precomputing_the_state_of_an_intersection() {
+        let ray = Ray::new(pt3(0.0, 0.0, -5.0), vec3(0.0, 0.0, 1.0));
+        let sphere = Shape::sphere();
+        let comps = Intersection::new(4.0, &sphere).prepare_computations(ray);
+        assert_eq!(comps.t, 4.0);
+        assert!(std::ptr::eq(comps.object, &sphere));
+        assert_eq!(comps.point, pt3(0.0, 0.0, -1.0));
+        assert_eq!(comps.eyev, vec3(0.0, 0.0, -1.0));
+        assert_eq!(comps.normalv, vec3(0.0, 0.0, -1.0));
+        assert!(!comps.inside);
+    }
+
+    #[test]
+    fn the_hit_when_an_intersection_occurs_on_the_inside() {
+        let ray = Ray::new(pt3(0.0, 0.0, 0.0), vec3(0.0, 0.0, 1.0));
+        let sphere = Shape::sphere();
+        let comps = Intersection::new(1.0, &sphere).prepare_computations(ray);
+        assert_eq!(comps.point, pt3(0.0, 0.0, 1.0));
+        assert_eq!(comps.eyev, vec3(0.0, 0.0, -1.0));
+        assert!(comps.inside);
+        // The normal would have been (0, 0, 1), but it's inverted.
+        assert_eq!(comps.normalv, vec3(0.0, 0.0, -1.0));
+    }
+
+    #[test]
+    fn the_hit_should_offset_the_point() {
+        let ray = Ray::new(pt3(0.0, 0.0, -5.0), vec3(0.0, 0.0, 1.0));
+        let sphere = Shape::sphere().with_transform(Transform::translation(0.0, 0.0, 1.0));
+        let comps = Intersection::new(5.0, &sphere).prepare_computations(ray);
+        assert!(comps.over_point.z() < -EPSILON / 2.0);
+        assert!(comps.point.z() > comps.over_point.z());
+    }
+
+    #[test]
+    fn a_bump_map_doesnt_push_the_hit_under_the_surface() {
+        use super::super::{BumpMap, Material, Rgb, Texture, UvMapping};
+
+        // Tilted so far that, seen at a grazing angle, the bumped normal faces
+        // away from the eye.
+        let tilted = Texture::solid(Rgb::new(0.95, 0.5, 0.6));
+        let plane = Shape::plane().with_material(Material {
+            bump: Some(BumpMap::normal_map(tilted, UvMapping::Planar)),
+            ..Material::default()
+        });
+        for direction in [vec3(10.0, -1.0, 0.0), vec3(-10.0, -1.0, 0.0)] {
+            let direction = direction.normalize();
+            let ray = Ray::new(pt3(0.0, 0.0, 0.0) - direction * 10.0, direction);
+            let comps = Intersection::new(10.0, &plane).prepare_computations(ray);
+            assert!(!comps.inside);
+            assert!(comps.over_point.y() > EPSILON / 2.0, "{direction:?}");
+        }
+    }
+}
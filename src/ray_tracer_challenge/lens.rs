@@ -0,0 +1,197 @@
+use std::f32::consts::{FRAC_PI_4, PI, TAU};
+
+use super::{pt2, Point2};
+
+/// The shape of a lens's opening, which is also the shape that out of focus
+/// highlights take.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ApertureShape {
+    Circle,
+    /// A regular polygon, like the opening made by a camera's aperture blades.
+    Polygon {
+        sides: u32,
+    },
+}
+
+impl ApertureShape {
+    /// Maps a point in the unit square onto the aperture, which has a radius
+    /// of 1.0. Evenly spread points in the square stay evenly spread.
+    pub fn point_on_aperture(&self, u: f32, v: f32) -> Point2 {
+        match *self {
+            ApertureShape::Circle => concentric_disk(u, v),
+            ApertureShape::Polygon { sides } => polygon(sides.max(3), u, v),
+        }
+    }
+}
+
+/// Shirley and Chiu's concentric mapping from the square to the disk, which
+/// keeps neighboring points close together.
+fn concentric_disk(u: f32, v: f32) -> Point2 {
+    let a = 2.0 * u - 1.0;
+    let b = 2.0 * v - 1.0;
+    if a == 0.0 && b == 0.0 {
+        return pt2(0.0, 0.0);
+    }
+    let (radius, angle) = if a.abs() > b.abs() {
+        (a, FRAC_PI_4 * (b / a))
+    } else {
+        (b, PI / 2.0 - FRAC_PI_4 * (a / b))
+    };
+    pt2(radius * angle.cos(), radius * angle.sin())
+}
+
+/// Picks one of the triangles between the polygon's center and its edges
+/// with `u`, then a uniformly distributed point within it.
+fn polygon(sides: u32, u: f32, v: f32) -> Point2 {
+    let scaled = u * sides as f32;
+    let side = (scaled.floor() as u32).min(sides - 1);
+    let along = scaled - side as f32;
+
+    let corner = |index: u32| {
+        let angle = TAU * index as f32 / sides as f32;
+        (angle.cos(), angle.sin())
+    };
+    let (x0, y0) = corner(side);
+    let (x1, y1) = corner(side + 1);
+    let distance = v.sqrt();
+    pt2(
+        distance * ((1.0 - along) * x0 + along * x1),
+        distance * ((1.0 - along) * y0 + along * y1),
+    )
+}
+
+/// A thin lens, which brings points `focal_distance` away from the camera into
+/// focus and blurs everything nearer or further. The wider the aperture, the
+/// blurrier things get.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Lens {
+    pub aperture_radius: f32,
+    pub focal_distance: f32,
+    pub aperture_shape: ApertureShape,
+    /// How many points on the lens to trace through for each pixel sample.
+    pub num_samples: u32,
+}
+
+impl Lens {
+    /// A lens with no aperture, which keeps everything in focus.
+    pub fn pinhole() -> Lens {
+        Lens {
+            aperture_radius: 0.0,
+            focal_distance: 1.0,
+            aperture_shape: ApertureShape::Circle,
+            num_samples: 1,
+        }
+    }
+
+    pub fn new(aperture_radius: f32, focal_distance: f32) -> Lens {
+        Lens {
+            aperture_radius,
+            focal_distance,
+            aperture_shape: ApertureShape::Circle,
+            num_samples: 16,
+        }
+    }
+
+    pub fn with_aperture_shape(mut self, aperture_shape: ApertureShape) -> Lens {
+        self.aperture_shape = aperture_shape;
+        self
+    }
+
+    pub fn with_num_samples(mut self, num_samples: u32) -> Lens {
+        self.num_samples = num_samples.max(1);
+        self
+    }
+
+    pub fn is_pinhole(&self) -> bool {
+        self.aperture_radius <= 0.0
+    }
+
+    /// The number of rays to trace for each pixel sample. A pinhole only needs
+    /// one, since every ray through it is the same.
+    pub fn samples_per_pixel_sample(&self) -> u32 {
+        if self.is_pinhole() {
+            1
+        } else {
+            self.num_samples
+        }
+    }
+
+    /// Maps a point in the unit square to a point on the lens, in the camera's
+    /// coordinates.
+    pub fn point_on_lens(&self, lens_sample: Point2) -> Point2 {
+        let point = self
+            .aperture_shape
+            .point_on_aperture(lens_sample.x(), lens_sample.y());
+        pt2(
+            point.x() * self.aperture_radius,
+            point.y() * self.aperture_radius,
+        )
+    }
+}
+
+impl Default for Lens {
+    fn default() -> Self {
+        Lens::pinhole()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::extensions::F32Extension;
+
+    fn grid(n: u32) -> impl Iterator<Item = (f32, f32)> {
+        (0..n).flat_map(move |y| {
+            (0..n).map(move |x| ((x as f32 + 0.5) / n as f32, (y as f32 + 0.5) / n as f32))
+        })
+    }
+
+    #[test]
+    fn the_default_lens_is_a_pinhole() {
+        let lens = Lens::default();
+        assert!(lens.is_pinhole());
+        assert_eq!(lens.samples_per_pixel_sample(), 1);
+        assert_eq!(lens.point_on_lens(pt2(0.3, 0.9)), pt2(0.0, 0.0));
+    }
+
+    #[test]
+    fn the_center_of_the_square_maps_to_the_center_of_the_aperture() {
+        let circle = ApertureShape::Circle.point_on_aperture(0.5, 0.5);
+        assert_eq!(circle, pt2(0.0, 0.0));
+        let hexagon = ApertureShape::Polygon { sides: 6 }.point_on_aperture(0.25, 0.0);
+        assert_eq!(hexagon, pt2(0.0, 0.0));
+    }
+
+    #[test]
+    fn points_on_a_circular_aperture_stay_inside_the_circle() {
+        for (u, v) in grid(16) {
+            let point = ApertureShape::Circle.point_on_aperture(u, v);
+            let distance = (point.x() * point.x() + point.y() * point.y()).sqrt();
+            assert!(distance <= 1.0 + 1e-5, "{point:?}");
+        }
+        let edge = ApertureShape::Circle.point_on_aperture(1.0, 0.5);
+        assert!(edge.x().almost_eq(1.0) && edge.y().almost_eq(0.0));
+    }
+
+    #[test]
+    fn points_on_a_polygonal_aperture_stay_inside_the_polygon() {
+        let sides = 6;
+        // The distance from a regular hexagon's center to the middle of an edge.
+        let apothem = (PI / sides as f32).cos();
+        for (u, v) in grid(16) {
+            let point = ApertureShape::Polygon { sides }.point_on_aperture(u, v);
+            let distance = (point.x() * point.x() + point.y() * point.y()).sqrt();
+            assert!(distance <= 1.0 + 1e-5, "{point:?}");
+            let angle = point.y().atan2(point.x()).rem_euclid(TAU / sides as f32);
+            let distance_to_edge = apothem / (angle - PI / sides as f32).cos();
+            assert!(distance <= distance_to_edge + 1e-5, "{point:?}");
+        }
+    }
+
+    #[test]
+    fn points_on_the_lens_are_scaled_by_the_aperture_radius() {
+        let lens = Lens::new(0.5, 10.0);
+        let point = lens.point_on_lens(pt2(1.0, 0.5));
+        assert!(point.x().almost_eq(0.5) && point.y().almost_eq(0.0));
+    }
+}
@@ -0,0 +1,220 @@
+use std::fmt::Debug;
+
+use super::{Point3, Rgb, Vec3, World};
+
+/// The direction and distance from a point being shaded to one point on a
+/// light. Each sample gets its own shadow ray.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LightSample {
+    /// Normalized.
+    pub direction: Vec3,
+    /// `f32::INFINITY` for lights that are infinitely far away.
+    pub distance: f32,
+    /// Scales the light's intensity along this sample. 1.0 for lights that
+    /// shine equally from every sample.
+    pub weight: f32,
+}
+
+impl LightSample {
+    pub fn toward(from: Point3, to: Point3) -> LightSample {
+        let v = to - from;
+        LightSample {
+            direction: v.normalize(),
+            distance: v.length(),
+            weight: 1.0,
+        }
+    }
+}
+
+/// How a light dims with distance. Its intensity is divided by
+/// `constant + linear * distance + quadratic * distance²`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Attenuation {
+    pub constant: f32,
+    pub linear: f32,
+    pub quadratic: f32,
+}
+
+impl Attenuation {
+    pub fn new(constant: f32, linear: f32, quadratic: f32) -> Attenuation {
+        Attenuation {
+            constant,
+            linear,
+            quadratic,
+        }
+    }
+
+    /// No dimming at any distance.
+    pub fn none() -> Attenuation {
+        Attenuation::new(1.0, 0.0, 0.0)
+    }
+
+    /// The fraction of the light left after traveling `distance`. Light from
+    /// infinitely far away, like the sun's, isn't attenuated.
+    pub fn factor(&self, distance: f32) -> f32 {
+        if distance.is_infinite() {
+            return 1.0;
+        }
+        let divisor = self.constant + self.linear * distance + self.quadratic * distance * distance;
+        1.0 / divisor.max(f32::EPSILON)
+    }
+}
+
+impl Default for Attenuation {
+    fn default() -> Self {
+        Attenuation::none()
+    }
+}
+
+/// Anything that can light a `World`.
+pub trait Light: Debug + Send + Sync {
+    fn intensity(&self) -> Rgb;
+
+    /// The points on the light that `point` is lit from.
+    fn samples(&self, point: Point3) -> Vec<LightSample>;
+
+    fn attenuation(&self) -> Attenuation {
+        Attenuation::none()
+    }
+
+    /// How much of the light's intensity arrives along `sample`, after
+    /// attenuation and the sample's weight.
+    fn strength(&self, sample: &LightSample) -> f32 {
+        self.attenuation().factor(sample.distance) * sample.weight
+    }
+
+    /// Whether materials add their `ambient` fraction of this light, to make
+    /// up for light bounced around the scene that isn't traced.
+    fn contributes_ambient(&self) -> bool {
+        true
+    }
+
+    /// How much of the light is aimed at `point`, ignoring anything in the
+    /// way, from 0.0 to 1.0. Most lights shine equally in every direction.
+    fn falloff(&self, _point: Point3) -> f32 {
+        1.0
+    }
+
+    /// The fraction of the light that reaches `point` at `time`, from 0.0 in
+    /// full shadow to 1.0 fully lit.
+    fn intensity_at(&self, point: Point3, time: f32, world: &World) -> f32 {
+        let falloff = self.falloff(point);
+        if falloff <= 0.0 {
+            return 0.0;
+        }
+        let samples = self.samples(point);
+        if samples.is_empty() {
+            return 0.0;
+        }
+        let num_visible = samples
+            .iter()
+            .filter(|&&sample| !world.is_occluded(point, sample, time))
+            .count();
+        falloff * num_visible as f32 / samples.len() as f32
+    }
+}
+
+/// A light with no size that shines equally in every direction, casting hard
+/// shadows.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PointLight {
+    pub position: Point3,
+    pub intensity: Rgb,
+    pub attenuation: Attenuation,
+}
+
+impl PointLight {
+    pub fn new(position: Point3, intensity: Rgb) -> PointLight {
+        PointLight {
+            position,
+            intensity,
+            attenuation: Attenuation::none(),
+        }
+    }
+
+    pub fn with_attenuation(mut self, attenuation: Attenuation) -> PointLight {
+        self.attenuation = attenuation;
+        self
+    }
+}
+
+impl Light for PointLight {
+    fn intensity(&self) -> Rgb {
+        self.intensity
+    }
+
+    fn attenuation(&self) -> Attenuation {
+        self.attenuation
+    }
+
+    fn samples(&self, point: Point3) -> Vec<LightSample> {
+        vec![LightSample::toward(point, self.position)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{default_world, pt3, vec3};
+    use super::*;
+
+    #[test]
+    fn a_point_light_has_a_position_and_intensity() {
+        let intensity = Rgb::new(1.0, 1.0, 1.0);
+        let light = PointLight::new(pt3(0.0, 0.0, 0.0), intensity);
+        assert_eq!(light.position, pt3(0.0, 0.0, 0.0));
+        assert_eq!(light.intensity(), intensity);
+    }
+
+    #[test]
+    fn lights_are_not_attenuated_by_default() {
+        let attenuation = Attenuation::default();
+        assert_eq!(attenuation.factor(0.0), 1.0);
+        assert_eq!(attenuation.factor(100.0), 1.0);
+        assert_eq!(attenuation.factor(f32::INFINITY), 1.0);
+    }
+
+    #[test]
+    fn attenuation_combines_constant_linear_and_quadratic_terms() {
+        let attenuation = Attenuation::new(1.0, 0.5, 0.25);
+        assert_eq!(attenuation.factor(0.0), 1.0);
+        assert_eq!(attenuation.factor(2.0), 1.0 / 3.0);
+        assert_eq!(attenuation.factor(4.0), 1.0 / 7.0);
+        assert_eq!(attenuation.factor(f32::INFINITY), 1.0);
+    }
+
+    #[test]
+    fn a_point_light_is_sampled_at_its_position() {
+        let light = PointLight::new(pt3(0.0, 3.0, 0.0), Rgb::new(1.0, 1.0, 1.0));
+        let samples = light.samples(pt3(0.0, -1.0, 0.0));
+        assert_eq!(
+            samples,
+            vec![LightSample {
+                direction: vec3(0.0, 1.0, 0.0),
+                distance: 4.0,
+                weight: 1.0,
+            }]
+        );
+    }
+
+    #[test]
+    fn point_lights_evaluate_the_light_intensity_at_a_given_point() {
+        let world = default_world();
+        let light = &world.lights[0];
+        let cases = [
+            (pt3(0.0, 1.0001, 0.0), 1.0),
+            (pt3(-1.0001, 0.0, 0.0), 1.0),
+            (pt3(0.0, 0.0, -1.0001), 1.0),
+            (pt3(0.0, 0.0, 1.0001), 0.0),
+            (pt3(1.0001, 0.0, 0.0), 0.0),
+            (pt3(0.0, -1.0001, 0.0), 0.0),
+            (pt3(0.0, 0.0, 0.0), 0.0),
+        ];
+        for (point, expected) in cases {
+            assert_eq!(
+                light.intensity_at(point, 0.0, &world),
+                expected,
+                "{point:?}"
+            );
+        }
+    }
+}
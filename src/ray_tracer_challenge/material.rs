@@ -0,0 +1,252 @@
+use super::{microfacet_lighting, BumpMap, Light, Pattern, Point3, Rgb, Vec3};
+
+/// Which reflection model `lighting` shades a material with.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Shading {
+    /// The book's Phong model, using `diffuse`, `specular` and `shininess`.
+    #[default]
+    Phong,
+    /// A physically based metallic/roughness model. See `microfacet_lighting`.
+    Microfacet { metallic: f32, roughness: f32 },
+}
+
+/// Surface properties. Shaded with the Phong reflection model by default.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Material {
+    pub shading: Shading,
+    pub color: Rgb,
+    /// Overrides `color` with one that varies across the surface. See
+    /// `Shape::material_at`.
+    pub pattern: Option<Pattern>,
+    /// Tilts the surface's normals. See `Shape::normal_at`.
+    pub bump: Option<BumpMap>,
+    /// Light reflected from the environment, as a fraction of `color`.
+    pub ambient: f32,
+    /// Light reflected from a matte surface.
+    pub diffuse: f32,
+    /// The brightness of highlights.
+    pub specular: f32,
+    /// Larger values give smaller, tighter highlights.
+    pub shininess: f32,
+    /// Light given off by the surface itself, which is added whether or not
+    /// anything lights it.
+    pub emission: Rgb,
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Material {
+            shading: Shading::Phong,
+            color: Rgb::new(1.0, 1.0, 1.0),
+            pattern: None,
+            bump: None,
+            ambient: 0.1,
+            diffuse: 0.9,
+            specular: 0.9,
+            shininess: 200.0,
+            emission: Rgb::black(),
+        }
+    }
+}
+
+impl Material {
+    /// A physically based material, as used by standard PBR assets. Metals
+    /// have a `metallic` of 1.0 and everything else 0.0, and `roughness`
+    /// runs from 0.0 for a mirror finish to 1.0 for fully matte.
+    pub fn microfacet(base_color: Rgb, metallic: f32, roughness: f32) -> Material {
+        Material {
+            shading: Shading::Microfacet {
+                metallic,
+                roughness,
+            },
+            color: base_color,
+            ..Material::default()
+        }
+    }
+
+    /// The fraction of light a matte version of the surface would reflect.
+    /// Metals absorb whatever they don't reflect as highlights, so they have
+    /// none.
+    pub fn albedo(&self) -> Rgb {
+        match self.shading {
+            Shading::Phong => self.color * self.diffuse,
+            Shading::Microfacet { metallic, .. } => self.color * (1.0 - metallic),
+        }
+    }
+}
+
+/// Shades `point` with the material's reflection model. See `phong_lighting`
+/// and `microfacet_lighting`.
+pub fn lighting(
+    material: &Material,
+    light: &dyn Light,
+    point: Point3,
+    eyev: Vec3,
+    normalv: Vec3,
+    light_intensity: f32,
+) -> Rgb {
+    match material.shading {
+        Shading::Phong => phong_lighting(material, light, point, eyev, normalv, light_intensity),
+        Shading::Microfacet { .. } => {
+            microfacet_lighting(material, light, point, eyev, normalv, light_intensity)
+        }
+    }
+}
+
+/// Shades `point` with the Phong reflection model, averaging the diffuse and
+/// specular contributions of every sample on the light. Each sample is
+/// attenuated by its distance from `point`.
+///
+/// `light_intensity` is the fraction of the light that reaches `point`, from
+/// 0.0 in full shadow to 1.0 fully lit. See `Light::intensity_at`.
+pub fn phong_lighting(
+    material: &Material,
+    light: &dyn Light,
+    point: Point3,
+    eyev: Vec3,
+    normalv: Vec3,
+    light_intensity: f32,
+) -> Rgb {
+    let effective_color = material.color * light.intensity();
+    let ambient = if light.contributes_ambient() {
+        effective_color * material.ambient
+    } else {
+        Rgb::black()
+    };
+
+    let samples = light.samples(point);
+    let mut total = Rgb::black();
+    for sample in &samples {
+        let lightv = sample.direction;
+        let light_dot_normal = lightv.dot(normalv);
+        // A negative number means the light is on the other side of the surface.
+        if light_dot_normal < 0.0 {
+            continue;
+        }
+        let mut contribution = effective_color * material.diffuse * light_dot_normal;
+
+        let reflectv = (-lightv).reflect(normalv);
+        let reflect_dot_eye = reflectv.dot(eyev);
+        // A negative number means the light reflects away from the eye.
+        if reflect_dot_eye > 0.0 {
+            let factor = reflect_dot_eye.powf(material.shininess);
+            contribution = contribution + light.intensity() * material.specular * factor;
+        }
+
+        total = total + contribution * light.strength(sample);
+    }
+
+    ambient + total * (light_intensity / samples.len().max(1) as f32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{pt3, vec3, Attenuation, PointLight};
+    use super::*;
+    use std::f32::consts::FRAC_1_SQRT_2;
+
+    fn white() -> Rgb {
+        Rgb::new(1.0, 1.0, 1.0)
+    }
+
+    fn assert_lighting(eyev: Vec3, light_position: Point3, light_intensity: f32, expected: Rgb) {
+        let material = Material::default();
+        let position = pt3(0.0, 0.0, 0.0);
+        let normalv = vec3(0.0, 0.0, -1.0);
+        let light = PointLight::new(light_position, white());
+        let actual = lighting(&material, &light, position, eyev, normalv, light_intensity);
+        let difference = (actual - expected).components().map(f32::abs);
+        assert!(
+            difference.iter().all(|&d| d < 0.0001),
+            "{actual:?} != {expected:?}"
+        );
+    }
+
+    #[test]
+    fn the_default_material() {
+        let material = Material::default();
+        assert_eq!(material.color, white());
+        assert_eq!(material.ambient, 0.1);
+        assert_eq!(material.diffuse, 0.9);
+        assert_eq!(material.specular, 0.9);
+        assert_eq!(material.shininess, 200.0);
+        assert_eq!(material.emission, Rgb::black());
+        assert_eq!(material.shading, Shading::Phong);
+        assert_eq!(material.pattern, None);
+        assert_eq!(material.bump, None);
+    }
+
+    #[test]
+    fn metals_have_no_albedo() {
+        let base_color = Rgb::new(1.0, 0.5, 0.25);
+        assert_eq!(Material::default().albedo(), white() * 0.9);
+        assert_eq!(
+            Material::microfacet(base_color, 0.0, 0.5).albedo(),
+            base_color
+        );
+        assert_eq!(
+            Material::microfacet(base_color, 1.0, 0.5).albedo(),
+            Rgb::black()
+        );
+    }
+
+    #[test]
+    fn lighting_with_the_eye_between_the_light_and_the_surface() {
+        let eyev = vec3(0.0, 0.0, -1.0);
+        assert_lighting(eyev, pt3(0.0, 0.0, -10.0), 1.0, Rgb::new(1.9, 1.9, 1.9));
+    }
+
+    #[test]
+    fn lighting_with_the_eye_between_light_and_surface_eye_offset_45_degrees() {
+        let eyev = vec3(0.0, FRAC_1_SQRT_2, -FRAC_1_SQRT_2);
+        assert_lighting(eyev, pt3(0.0, 0.0, -10.0), 1.0, Rgb::new(1.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn lighting_with_eye_opposite_surface_light_offset_45_degrees() {
+        let eyev = vec3(0.0, 0.0, -1.0);
+        assert_lighting(
+            eyev,
+            pt3(0.0, 10.0, -10.0),
+            1.0,
+            Rgb::new(0.7364, 0.7364, 0.7364),
+        );
+    }
+
+    #[test]
+    fn lighting_with_eye_in_the_path_of_the_reflection_vector() {
+        let eyev = vec3(0.0, -FRAC_1_SQRT_2, -FRAC_1_SQRT_2);
+        assert_lighting(
+            eyev,
+            pt3(0.0, 10.0, -10.0),
+            1.0,
+            Rgb::new(1.6364, 1.6364, 1.6364),
+        );
+    }
+
+    #[test]
+    fn lighting_with_the_light_behind_the_surface() {
+        let eyev = vec3(0.0, 0.0, -1.0);
+        assert_lighting(eyev, pt3(0.0, 0.0, 10.0), 1.0, Rgb::new(0.1, 0.1, 0.1));
+    }
+
+    #[test]
+    fn lighting_with_the_surface_in_shadow() {
+        let eyev = vec3(0.0, 0.0, -1.0);
+        assert_lighting(eyev, pt3(0.0, 0.0, -10.0), 0.0, Rgb::new(0.1, 0.1, 0.1));
+    }
+
+    #[test]
+    fn lighting_is_attenuated_by_distance_but_ambient_is_not() {
+        let material = Material::default();
+        let position = pt3(0.0, 0.0, 0.0);
+        let eyev = vec3(0.0, 0.0, -1.0);
+        let normalv = vec3(0.0, 0.0, -1.0);
+        let light = PointLight::new(pt3(0.0, 0.0, -10.0), white())
+            .with_attenuation(Attenuation::new(1.0, 0.1, 0.0));
+        let actual = lighting(&material, &light, position, eyev, normalv, 1.0);
+        // Diffuse and specular add up to 1.8 at full strength, and the light is
+        // attenuated to half strength at a distance of 10.
+        assert_eq!(actual, Rgb::new(1.0, 1.0, 1.0));
+    }
+}
@@ -0,0 +1,209 @@
+use std::f32::consts::PI;
+
+use super::{phong_lighting, Light, Material, Point3, Rgb, Shading, Vec3};
+
+/// How reflective a dielectric like plastic or paint is when seen head on.
+const DIELECTRIC_REFLECTANCE: f32 = 0.04;
+
+/// The GGX (Trowbridge-Reitz) distribution of microfacet normals: how many of
+/// the surface's tiny facets face halfway between the light and the eye.
+/// `roughness` is perceptual, so it's squared before use.
+pub fn ggx_distribution(n_dot_h: f32, roughness: f32) -> f32 {
+    let alpha_squared = alpha(roughness).powi(2);
+    let denominator = n_dot_h * n_dot_h * (alpha_squared - 1.0) + 1.0;
+    alpha_squared / (PI * denominator * denominator)
+}
+
+/// Smith's geometry term for GGX: the fraction of facets that are neither
+/// hidden from the eye nor shadowed from the light by other facets.
+pub fn smith_geometry(n_dot_v: f32, n_dot_l: f32, roughness: f32) -> f32 {
+    let alpha_squared = alpha(roughness).powi(2);
+    let g1 = |n_dot_x: f32| {
+        2.0 * n_dot_x
+            / (n_dot_x + (alpha_squared + (1.0 - alpha_squared) * n_dot_x * n_dot_x).sqrt())
+    };
+    g1(n_dot_v) * g1(n_dot_l)
+}
+
+/// Schlick's approximation of how much light is reflected rather than
+/// refracted, which rises towards 1.0 at grazing angles. `f0` is the
+/// reflectance when seen head on.
+pub fn schlick_fresnel(f0: Rgb, v_dot_h: f32) -> Rgb {
+    let factor = (1.0 - v_dot_h).clamp(0.0, 1.0).powi(5);
+    f0 + (Rgb::new(1.0, 1.0, 1.0) - f0) * factor
+}
+
+/// Keeps perfectly smooth surfaces from turning the distribution into a
+/// spike that no sample ever hits.
+fn alpha(roughness: f32) -> f32 {
+    roughness.clamp(0.01, 1.0).powi(2)
+}
+
+/// Shades `point` with a metallic/roughness microfacet model: GGX for the
+/// distribution of facets, Smith for shadowing between them and Schlick for
+/// Fresnel. Matches the standard PBR workflow, where `material.color` is the
+/// base color.
+///
+/// Lights are scaled like `lighting`'s, so a white matte surface facing a
+/// light of intensity 1.0 is lit to about 1.0. `material.ambient` is used as
+/// with Phong, and the Phong-only fields are ignored. Materials shaded with
+/// `Shading::Phong` are passed on to `phong_lighting`.
+pub fn microfacet_lighting(
+    material: &Material,
+    light: &dyn Light,
+    point: Point3,
+    eyev: Vec3,
+    normalv: Vec3,
+    light_intensity: f32,
+) -> Rgb {
+    let Shading::Microfacet {
+        metallic,
+        roughness,
+    } = material.shading
+    else {
+        return phong_lighting(material, light, point, eyev, normalv, light_intensity);
+    };
+    let base_color = material.color;
+    let ambient = if light.contributes_ambient() {
+        base_color * light.intensity() * material.ambient
+    } else {
+        Rgb::black()
+    };
+
+    let dielectric = Rgb::new(
+        DIELECTRIC_REFLECTANCE,
+        DIELECTRIC_REFLECTANCE,
+        DIELECTRIC_REFLECTANCE,
+    );
+    let f0 = dielectric * (1.0 - metallic) + base_color * metallic;
+    let n_dot_v = normalv.dot(eyev).max(1e-4);
+
+    let samples = light.samples(point);
+    let mut total = Rgb::black();
+    for sample in &samples {
+        let lightv = sample.direction;
+        let n_dot_l = normalv.dot(lightv);
+        if n_dot_l <= 0.0 {
+            continue;
+        }
+        let halfway = (lightv + eyev).normalize();
+        let n_dot_h = normalv.dot(halfway).max(0.0);
+        let v_dot_h = eyev.dot(halfway).max(0.0);
+
+        let fresnel = schlick_fresnel(f0, v_dot_h);
+        let distribution = ggx_distribution(n_dot_h, roughness);
+        let geometry = smith_geometry(n_dot_v, n_dot_l, roughness);
+        let specular = fresnel * (distribution * geometry / (4.0 * n_dot_v * n_dot_l));
+
+        // Whatever isn't reflected off the surface is scattered beneath it,
+        // except by metals, which absorb it.
+        let scattered = (Rgb::new(1.0, 1.0, 1.0) - fresnel) * (1.0 - metallic);
+        let diffuse = scattered * base_color * (1.0 / PI);
+
+        // Multiplying by π undoes the 1/π that keeps the BRDF energy
+        // conserving, so lights have the same brightness as with Phong.
+        let brdf = diffuse + specular;
+        let strength = light.strength(sample);
+        total = total + brdf * light.intensity() * (PI * n_dot_l * strength);
+    }
+
+    ambient + total * (light_intensity / samples.len().max(1) as f32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{pt3, vec3, PointLight};
+    use super::*;
+
+    fn shade(material: &Material, light_position: Point3, eyev: Vec3) -> Rgb {
+        let light = PointLight::new(light_position, Rgb::new(1.0, 1.0, 1.0));
+        let normalv = vec3(0.0, 0.0, -1.0);
+        let point = pt3(0.0, 0.0, 0.0);
+        microfacet_lighting(material, &light, point, eyev, normalv, 1.0)
+    }
+
+    #[test]
+    fn the_ggx_distribution_is_normalized() {
+        // The projected area of the facets must add up to the area of the
+        // surface, so ∫ D(h) (n·h) dω over the hemisphere is 1.
+        for roughness in [0.3, 0.6, 1.0] {
+            let steps = 2000;
+            let mut total = 0.0;
+            for i in 0..steps {
+                let theta = (i as f32 + 0.5) / steps as f32 * PI / 2.0;
+                let n_dot_h = theta.cos();
+                let solid_angle = 2.0 * PI * theta.sin() * (PI / 2.0 / steps as f32);
+                total += ggx_distribution(n_dot_h, roughness) * n_dot_h * solid_angle;
+            }
+            assert!((total - 1.0).abs() < 0.01, "{roughness}: {total}");
+        }
+    }
+
+    #[test]
+    fn rougher_surfaces_have_wider_highlights() {
+        assert!(ggx_distribution(1.0, 0.2) > ggx_distribution(1.0, 0.8));
+        assert!(ggx_distribution(0.8, 0.2) < ggx_distribution(0.8, 0.8));
+    }
+
+    #[test]
+    fn smith_geometry_shadows_grazing_angles() {
+        assert!((smith_geometry(1.0, 1.0, 0.5) - 1.0).abs() < 0.0001);
+        assert!(smith_geometry(0.1, 1.0, 0.5) < 0.9);
+        assert!(smith_geometry(0.1, 1.0, 1.0) < smith_geometry(0.1, 1.0, 0.5));
+    }
+
+    #[test]
+    fn schlick_fresnel_rises_towards_grazing_angles() {
+        let f0 = Rgb::new(0.04, 0.04, 0.04);
+        assert_eq!(schlick_fresnel(f0, 1.0), f0);
+        assert_eq!(schlick_fresnel(f0, 0.0), Rgb::new(1.0, 1.0, 1.0));
+        assert!(schlick_fresnel(f0, 0.5).red() > 0.04);
+    }
+
+    #[test]
+    fn a_rough_dielectric_is_lit_about_as_brightly_as_phong() {
+        let material = Material {
+            ambient: 0.0,
+            ..Material::microfacet(Rgb::new(1.0, 1.0, 1.0), 0.0, 1.0)
+        };
+        let rgb = shade(&material, pt3(0.0, 0.0, -10.0), vec3(0.0, 0.0, -1.0));
+        // 96% scattered diffusely, plus a faint, wide highlight.
+        assert!((rgb.red() - 0.97).abs() < 0.001, "{rgb:?}");
+    }
+
+    #[test]
+    fn metals_tint_their_highlights_and_have_no_diffuse() {
+        let material = Material {
+            ambient: 0.0,
+            ..Material::microfacet(Rgb::new(1.0, 0.5, 0.0), 1.0, 0.3)
+        };
+        let highlight = shade(&material, pt3(0.0, 0.0, -10.0), vec3(0.0, 0.0, -1.0));
+        assert!(highlight.red() > 1.0, "{highlight:?}");
+        assert!((highlight.green() - highlight.red() * 0.5).abs() < 0.001);
+        assert!(highlight.blue().abs() < 0.0001);
+
+        // Away from the highlight, there's no diffuse reflection to see, only
+        // the highlight's faint tail.
+        let eyev = vec3(0.0, 0.8, -0.6);
+        let off_highlight = shade(&material, pt3(0.0, 0.0, -10.0), eyev);
+        assert!(
+            off_highlight.red() < highlight.red() * 0.01,
+            "{off_highlight:?}"
+        );
+    }
+
+    #[test]
+    fn lighting_uses_the_materials_shading_model() {
+        let material = Material::microfacet(Rgb::new(1.0, 1.0, 1.0), 0.0, 1.0);
+        let light = PointLight::new(pt3(0.0, 0.0, -10.0), Rgb::new(1.0, 1.0, 1.0));
+        let eyev = vec3(0.0, 0.0, -1.0);
+        let normalv = vec3(0.0, 0.0, -1.0);
+        let point = pt3(0.0, 0.0, 0.0);
+        let rgb = super::super::lighting(&material, &light, point, eyev, normalv, 1.0);
+        let expected = shade(&material, pt3(0.0, 0.0, -10.0), eyev);
+        assert_eq!(rgb, expected);
+        let phong = Material::default();
+        let phong_rgb = super::super::lighting(&phong, &light, point, eyev, normalv, 1.0);
+        assert_ne!(rgb, phong_rgb);
+    }
+}
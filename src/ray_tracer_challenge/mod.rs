@@ -1,5 +1,53 @@
 #![allow(unused_imports)]
 
+mod adaptive_sampling;
+pub use adaptive_sampling::*;
+
+mod area_light;
+pub use area_light::*;
+
+mod background;
+pub use background::*;
+
+mod bump_map;
+pub use bump_map::*;
+
+mod camera;
+pub use camera::*;
+
+mod cube_map;
+pub use cube_map::*;
+
+mod directional_light;
+pub use directional_light::*;
+
+mod intersection;
+pub use intersection::*;
+
+mod lens;
+pub use lens::*;
+
+mod light;
+pub use light::*;
+
+mod material;
+pub use material::*;
+
+mod microfacet;
+pub use microfacet::*;
+
+mod motion;
+pub use motion::*;
+
+mod noise;
+pub use noise::*;
+
+mod pattern;
+pub use pattern::*;
+
+mod path_tracer;
+pub use path_tracer::*;
+
 mod point2;
 pub use point2::*;
 
@@ -12,17 +60,43 @@ pub use point2_and_vec2::*;
 mod point3_and_vec3;
 pub use point3_and_vec3::*;
 
-mod projectiles;
-pub use projectiles::*;
+mod ray;
+pub use ray::*;
 
 mod rgb;
 pub use rgb::*;
 
+mod sampling;
+pub use sampling::*;
+
+pub mod sdf;
+
+mod shape;
+pub use shape::*;
+
+mod shape_light;
+pub use shape_light::*;
+
 mod size2;
 pub use size2::*;
 
+mod spot_light;
+pub use spot_light::*;
+
+mod texture;
+pub use texture::*;
+
+mod texture_map;
+pub use texture_map::*;
+
+mod transform;
+pub use transform::*;
+
 mod vec2;
 pub use vec2::*;
 
 mod vec3;
 pub use vec3::*;
+
+mod world;
+pub use world::*;
@@ -0,0 +1,77 @@
+use super::Transform;
+
+/// How a shape's transform changes over time. Times are the same ones the
+/// camera's `shutter_open` and `shutter_close` are given in.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Motion {
+    /// Moves steadily from `start` at time 0.0 to `end` at time 1.0.
+    Linear { start: Transform, end: Transform },
+    /// Passes through each transform at its time, moving steadily between
+    /// them. Holds still before the first keyframe and after the last.
+    Keyframes(Vec<(f32, Transform)>),
+}
+
+impl Motion {
+    pub fn linear(start: Transform, end: Transform) -> Motion {
+        Motion::Linear { start, end }
+    }
+
+    /// Keyframes can be given in any order.
+    pub fn keyframes(mut keyframes: Vec<(f32, Transform)>) -> Motion {
+        keyframes.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+        Motion::Keyframes(keyframes)
+    }
+
+    pub fn at(&self, time: f32) -> Transform {
+        match self {
+            Motion::Linear { start, end } => start.interpolate(*end, time.clamp(0.0, 1.0)),
+            Motion::Keyframes(keyframes) => {
+                let Some(&(first_time, first)) = keyframes.first() else {
+                    return Transform::identity();
+                };
+                if time <= first_time {
+                    return first;
+                }
+                for pair in keyframes.windows(2) {
+                    let (start_time, start) = pair[0];
+                    let (end_time, end) = pair[1];
+                    if time <= end_time {
+                        let t = (time - start_time) / (end_time - start_time);
+                        return start.interpolate(end, t);
+                    }
+                }
+                keyframes[keyframes.len() - 1].1
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn linear_motion_moves_from_start_to_end() {
+        let start = Transform::translation(0.0, 0.0, 0.0);
+        let end = Transform::translation(2.0, 0.0, 0.0);
+        let motion = Motion::linear(start, end);
+        assert_eq!(motion.at(0.0), start);
+        assert_eq!(motion.at(0.25), Transform::translation(0.5, 0.0, 0.0));
+        assert_eq!(motion.at(1.0), end);
+        assert_eq!(motion.at(2.0), end);
+    }
+
+    #[test]
+    fn keyframed_motion_passes_through_every_keyframe() {
+        let motion = Motion::keyframes(vec![
+            (1.0, Transform::translation(0.0, 0.0, 0.0)),
+            (0.0, Transform::translation(0.0, 0.0, 0.0)),
+            (0.5, Transform::translation(0.0, 4.0, 0.0)),
+        ]);
+        assert_eq!(motion.at(-1.0), Transform::translation(0.0, 0.0, 0.0));
+        assert_eq!(motion.at(0.25), Transform::translation(0.0, 2.0, 0.0));
+        assert_eq!(motion.at(0.5), Transform::translation(0.0, 4.0, 0.0));
+        assert_eq!(motion.at(0.75), Transform::translation(0.0, 2.0, 0.0));
+        assert_eq!(motion.at(1.5), Transform::translation(0.0, 0.0, 0.0));
+    }
+}
@@ -0,0 +1,362 @@
+use std::f32::consts::PI;
+use std::fmt;
+use std::sync::Arc;
+
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use rand_pcg::Pcg32;
+
+use super::{pt3, Point3, Vec3};
+
+/// Seeded 3D gradient noise, for procedural textures. The same seed always
+/// gives the same noise, so renders are repeatable. Cheap to clone.
+#[derive(Clone, PartialEq)]
+pub struct Noise {
+    seed: u64,
+    /// How many layers of detail `fbm` and `turbulence` add up.
+    pub octaves: u32,
+    /// Ken Perlin's permutation table, shuffled by the seed and repeated
+    /// twice so lookups don't need wrapping.
+    permutation: Arc<[u8; 512]>,
+}
+
+impl Noise {
+    pub fn new(seed: u64) -> Noise {
+        let mut table: Vec<u8> = (0..=255).collect();
+        table.shuffle(&mut Pcg32::seed_from_u64(seed));
+        let mut permutation = [0; 512];
+        for (index, value) in permutation.iter_mut().enumerate() {
+            *value = table[index % 256];
+        }
+        Noise {
+            seed,
+            octaves: 6,
+            permutation: Arc::new(permutation),
+        }
+    }
+
+    pub fn with_octaves(mut self, octaves: u32) -> Noise {
+        self.octaves = octaves.max(1);
+        self
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    fn hash(&self, index: i32) -> usize {
+        self.permutation[index as usize] as usize
+    }
+
+    /// Ken Perlin's improved noise. Roughly -1.0 to 1.0, and 0.0 at every
+    /// point with whole number coordinates.
+    pub fn perlin(&self, point: Point3) -> f32 {
+        let (x, y, z) = (point.x(), point.y(), point.z());
+        let (x0, y0, z0) = (x.floor(), y.floor(), z.floor());
+        let (xi, yi, zi) = (x0 as i32 & 255, y0 as i32 & 255, z0 as i32 & 255);
+        let (x, y, z) = (x - x0, y - y0, z - z0);
+        let (u, v, w) = (fade(x), fade(y), fade(z));
+
+        let a = self.hash(xi) as i32 + yi;
+        let aa = self.hash(a) as i32 + zi;
+        let ab = self.hash(a + 1) as i32 + zi;
+        let b = self.hash(xi + 1) as i32 + yi;
+        let ba = self.hash(b) as i32 + zi;
+        let bb = self.hash(b + 1) as i32 + zi;
+
+        let corner = |index: i32, dx: f32, dy: f32, dz: f32| {
+            perlin_gradient(self.hash(index), x - dx, y - dy, z - dz)
+        };
+        lerp(
+            w,
+            lerp(
+                v,
+                lerp(u, corner(aa, 0.0, 0.0, 0.0), corner(ba, 1.0, 0.0, 0.0)),
+                lerp(u, corner(ab, 0.0, 1.0, 0.0), corner(bb, 1.0, 1.0, 0.0)),
+            ),
+            lerp(
+                v,
+                lerp(
+                    u,
+                    corner(aa + 1, 0.0, 0.0, 1.0),
+                    corner(ba + 1, 1.0, 0.0, 1.0),
+                ),
+                lerp(
+                    u,
+                    corner(ab + 1, 0.0, 1.0, 1.0),
+                    corner(bb + 1, 1.0, 1.0, 1.0),
+                ),
+            ),
+        )
+    }
+
+    /// Ken Perlin's simplex noise, following Stefan Gustavson's "Simplex
+    /// noise demystified". Roughly -1.0 to 1.0. Cheaper than `perlin` and
+    /// without its grid-aligned artifacts.
+    pub fn simplex(&self, point: Point3) -> f32 {
+        const F3: f32 = 1.0 / 3.0;
+        const G3: f32 = 1.0 / 6.0;
+
+        // Skew into the grid of simplices to find which one the point is in.
+        let (x, y, z) = (point.x(), point.y(), point.z());
+        let skew = (x + y + z) * F3;
+        let (i, j, k) = ((x + skew).floor(), (y + skew).floor(), (z + skew).floor());
+        let unskew = (i + j + k) * G3;
+        let x0 = x - (i - unskew);
+        let y0 = y - (j - unskew);
+        let z0 = z - (k - unskew);
+
+        // The simplex's second and third corners, relative to its first.
+        let ((i1, j1, k1), (i2, j2, k2)) = if x0 >= y0 {
+            if y0 >= z0 {
+                ((1, 0, 0), (1, 1, 0))
+            } else if x0 >= z0 {
+                ((1, 0, 0), (1, 0, 1))
+            } else {
+                ((0, 0, 1), (1, 0, 1))
+            }
+        } else if y0 < z0 {
+            ((0, 0, 1), (0, 1, 1))
+        } else if x0 < z0 {
+            ((0, 1, 0), (0, 1, 1))
+        } else {
+            ((0, 1, 0), (1, 1, 0))
+        };
+
+        let (ii, jj, kk) = (i as i32 & 255, j as i32 & 255, k as i32 & 255);
+        let corners = [
+            ((0, 0, 0), x0, y0, z0),
+            (
+                (i1, j1, k1),
+                x0 - i1 as f32 + G3,
+                y0 - j1 as f32 + G3,
+                z0 - k1 as f32 + G3,
+            ),
+            (
+                (i2, j2, k2),
+                x0 - i2 as f32 + 2.0 * G3,
+                y0 - j2 as f32 + 2.0 * G3,
+                z0 - k2 as f32 + 2.0 * G3,
+            ),
+            (
+                (1, 1, 1),
+                x0 - 1.0 + 3.0 * G3,
+                y0 - 1.0 + 3.0 * G3,
+                z0 - 1.0 + 3.0 * G3,
+            ),
+        ];
+
+        let total: f32 = corners
+            .into_iter()
+            .map(|((di, dj, dk), x, y, z)| {
+                let falloff = 0.6 - x * x - y * y - z * z;
+                if falloff < 0.0 {
+                    return 0.0;
+                }
+                let hash =
+                    self.hash(ii + di + self.hash(jj + dj + self.hash(kk + dk) as i32) as i32);
+                let [gx, gy, gz] = SIMPLEX_GRADIENTS[hash % 12];
+                falloff.powi(4) * (gx * x + gy * y + gz * z)
+            })
+            .sum();
+        32.0 * total
+    }
+
+    /// Fractional Brownian motion: `octaves` layers of `perlin` noise, each
+    /// twice the frequency and half the amplitude of the last. Roughly -1.0
+    /// to 1.0.
+    pub fn fbm(&self, point: Point3) -> f32 {
+        self.sum_octaves(point, |noise| noise)
+    }
+
+    /// Like `fbm`, but adds up the absolute value of each layer, which gives
+    /// sharp creases where the noise crosses zero. 0.0 to roughly 1.0.
+    pub fn turbulence(&self, point: Point3) -> f32 {
+        self.sum_octaves(point, f32::abs)
+    }
+
+    fn sum_octaves(&self, point: Point3, layer: impl Fn(f32) -> f32) -> f32 {
+        let mut total = 0.0;
+        let mut amplitude = 1.0;
+        let mut frequency = 1.0;
+        let mut total_amplitude = 0.0;
+        for _ in 0..self.octaves {
+            let scaled = pt3(
+                point.x() * frequency,
+                point.y() * frequency,
+                point.z() * frequency,
+            );
+            total += layer(self.perlin(scaled)) * amplitude;
+            total_amplitude += amplitude;
+            amplitude *= 0.5;
+            frequency *= 2.0;
+        }
+        total / total_amplitude
+    }
+
+    /// Three independent noise values, for nudging points in a random but
+    /// smoothly varying direction.
+    pub fn vector(&self, point: Point3) -> Vec3 {
+        // Offsetting by distant, non-integer amounts decorrelates the axes.
+        let offset =
+            |dx: f32, dy: f32, dz: f32| pt3(point.x() + dx, point.y() + dy, point.z() + dz);
+        Vec3::new(
+            self.fbm(point),
+            self.fbm(offset(31.416, 47.853, 12.793)),
+            self.fbm(offset(-71.261, 8.337, 53.071)),
+        )
+    }
+
+    /// Veins of marble running across the x axis, from 0.0 to 1.0.
+    pub fn marble(&self, point: Point3) -> f32 {
+        let veins = ((point.x() + 4.0 * self.turbulence(point)) * PI).sin();
+        0.5 + 0.5 * veins
+    }
+
+    /// Growth rings of wood around the y axis, one per unit, from 0.0 to 1.0.
+    pub fn wood(&self, point: Point3) -> f32 {
+        let radius = (point.x() * point.x() + point.z() * point.z()).sqrt();
+        let rings = radius + 0.15 * self.fbm(point);
+        rings - rings.floor()
+    }
+
+    /// Billowing clouds, from 0.0 to 1.0.
+    pub fn clouds(&self, point: Point3) -> f32 {
+        (0.5 + 0.5 * self.fbm(point)).clamp(0.0, 1.0)
+    }
+}
+
+impl fmt::Debug for Noise {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Noise")
+            .field("seed", &self.seed)
+            .field("octaves", &self.octaves)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The midpoints of a cube's edges.
+const SIMPLEX_GRADIENTS: [[f32; 3]; 12] = [
+    [1.0, 1.0, 0.0],
+    [-1.0, 1.0, 0.0],
+    [1.0, -1.0, 0.0],
+    [-1.0, -1.0, 0.0],
+    [1.0, 0.0, 1.0],
+    [-1.0, 0.0, 1.0],
+    [1.0, 0.0, -1.0],
+    [-1.0, 0.0, -1.0],
+    [0.0, 1.0, 1.0],
+    [0.0, -1.0, 1.0],
+    [0.0, 1.0, -1.0],
+    [0.0, -1.0, -1.0],
+];
+
+/// 6t⁵ - 15t⁴ + 10t³, which eases in and out so the noise is smooth across
+/// grid cells.
+fn fade(t: f32) -> f32 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn lerp(t: f32, a: f32, b: f32) -> f32 {
+    a + t * (b - a)
+}
+
+/// The dot product of one of twelve gradient directions, picked by `hash`,
+/// with `(x, y, z)`.
+fn perlin_gradient(hash: usize, x: f32, y: f32, z: f32) -> f32 {
+    let h = hash & 15;
+    let u = if h < 8 { x } else { y };
+    let v = if h < 4 {
+        y
+    } else if h == 12 || h == 14 {
+        x
+    } else {
+        z
+    };
+    let u = if h & 1 == 0 { u } else { -u };
+    let v = if h & 2 == 0 { v } else { -v };
+    u + v
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grid() -> impl Iterator<Item = Point3> {
+        (0..20).flat_map(|x| {
+            (0..20).flat_map(move |y| {
+                (0..5).map(move |z| pt3(x as f32 * 0.37, y as f32 * 0.29 - 3.0, z as f32 * 1.13))
+            })
+        })
+    }
+
+    #[test]
+    fn noise_is_the_same_for_the_same_seed() {
+        let a = Noise::new(7);
+        let b = Noise::new(7);
+        let c = Noise::new(8);
+        for point in grid() {
+            assert_eq!(a.perlin(point), b.perlin(point));
+            assert_eq!(a.simplex(point), b.simplex(point));
+        }
+        // Any one point could match by chance, but not all of them.
+        assert!(grid().any(|point| a.perlin(point) != c.perlin(point)));
+        assert!(grid().any(|point| a.simplex(point) != c.simplex(point)));
+    }
+
+    #[test]
+    fn perlin_noise_is_zero_on_the_grid() {
+        let noise = Noise::new(1);
+        for point in [
+            pt3(0.0, 0.0, 0.0),
+            pt3(3.0, -2.0, 7.0),
+            pt3(-5.0, 1.0, 300.0),
+        ] {
+            assert_eq!(noise.perlin(point), 0.0, "{point:?}");
+        }
+    }
+
+    #[test]
+    fn noise_stays_in_range_and_varies() {
+        let noise = Noise::new(42);
+        let perlin: Vec<f32> = grid().map(|point| noise.perlin(point)).collect();
+        let simplex: Vec<f32> = grid().map(|point| noise.simplex(point)).collect();
+        for values in [perlin, simplex] {
+            assert!(values.iter().all(|value| (-1.0..=1.0).contains(value)));
+            let max = values.iter().cloned().fold(f32::MIN, f32::max);
+            let min = values.iter().cloned().fold(f32::MAX, f32::min);
+            assert!(max > 0.3 && min < -0.3, "{min} {max}");
+        }
+    }
+
+    #[test]
+    fn noise_is_smooth() {
+        let noise = Noise::new(3);
+        for point in grid() {
+            let nearby = pt3(point.x() + 0.001, point.y(), point.z() - 0.001);
+            assert!((noise.perlin(point) - noise.perlin(nearby)).abs() < 0.01);
+            assert!((noise.simplex(point) - noise.simplex(nearby)).abs() < 0.03);
+        }
+    }
+
+    #[test]
+    fn fbm_and_turbulence_stay_in_range() {
+        let noise = Noise::new(5).with_octaves(4);
+        for point in grid() {
+            let fbm = noise.fbm(point);
+            assert!((-1.0..=1.0).contains(&fbm), "{fbm}");
+            let turbulence = noise.turbulence(point);
+            assert!((0.0..=1.0).contains(&turbulence), "{turbulence}");
+        }
+    }
+
+    #[test]
+    fn procedural_textures_stay_between_zero_and_one() {
+        let noise = Noise::new(9);
+        for point in grid() {
+            for value in [noise.marble(point), noise.wood(point), noise.clouds(point)] {
+                assert!((0.0..=1.0).contains(&value), "{value}");
+            }
+        }
+    }
+}
@@ -0,0 +1,311 @@
+use rand::Rng;
+use rand_pcg::Pcg32;
+
+use crate::library::canvas::Canvas;
+use crate::library::tile_renderer::TileRenderer;
+
+use super::{
+    cosine_weighted_hemisphere, hit, pt2, rng_for_pixel, Camera, Computations, Ray, Rgb,
+    ShapeLight, World,
+};
+
+/// `Sampling` and `Camera` use the first two random streams in each pixel, so
+/// passes start after them.
+const FIRST_PASS_STREAM: u64 = 2;
+
+/// An alternative to `World::color_at` that renders global illumination by
+/// following random paths as they bounce from surface to surface. Each pass
+/// traces one path per pixel, and the image converges as passes accumulate.
+///
+/// Surfaces are treated as perfectly matte, reflecting `Material::albedo` of
+/// the light that hits them, so microfacet highlights are left out. Lights and emissive spheres and cubes are sampled
+/// directly at every bounce, and other emissive surfaces and the background
+/// light the scene when paths hit them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PathTracer {
+    /// Every path ends after this many bounces.
+    pub max_bounces: u32,
+    /// How many bounces every path gets before Russian roulette can end it.
+    pub min_bounces: u32,
+}
+
+impl PathTracer {
+    pub fn new() -> PathTracer {
+        PathTracer {
+            max_bounces: 16,
+            min_bounces: 3,
+        }
+    }
+
+    pub fn with_max_bounces(mut self, max_bounces: u32) -> PathTracer {
+        self.max_bounces = max_bounces;
+        self
+    }
+
+    pub fn with_min_bounces(mut self, min_bounces: u32) -> PathTracer {
+        self.min_bounces = min_bounces;
+        self
+    }
+
+    /// Estimates the light arriving along `ray` by following one random path.
+    pub fn radiance(&self, world: &World, ray: Ray, rng: &mut Pcg32) -> Rgb {
+        let mut radiance = Rgb::black();
+        let mut throughput = Rgb::new(1.0, 1.0, 1.0);
+        let mut ray = ray;
+
+        for bounce in 0..=self.max_bounces {
+            let intersections = world.intersect(ray);
+            let Some(hit) = hit(&intersections) else {
+                radiance =
+                    radiance + throughput * world.background.color(ray.direction.normalize());
+                break;
+            };
+            let comps = hit.prepare_computations(ray);
+            let material = comps.object.material_at(comps.point, comps.time);
+            let albedo = material.albedo();
+
+            // Emissive shapes that can be sampled were already counted as
+            // direct light at the previous bounce.
+            if bounce == 0 || !ShapeLight::can_sample(comps.object) {
+                radiance = radiance + throughput * material.emission;
+            }
+            radiance = radiance + throughput * albedo * direct_irradiance(world, &comps);
+
+            // Cosine-weighted sampling cancels out the cosine and the matte
+            // surface's 1/π, leaving just the albedo.
+            throughput = throughput * albedo;
+
+            // Russian roulette: end dim paths early, and make up for it by
+            // brightening the ones that survive, so the average is unchanged.
+            if bounce >= self.min_bounces {
+                let [red, green, blue] = throughput.components();
+                let survival_probability = red.max(green).max(blue).clamp(0.05, 1.0);
+                if rng.gen::<f32>() >= survival_probability {
+                    break;
+                }
+                throughput = throughput * (1.0 / survival_probability);
+            }
+
+            let direction = cosine_weighted_hemisphere(comps.normalv, rng.gen(), rng.gen());
+            ray = Ray::new(comps.over_point, direction).with_time(ray.time);
+        }
+
+        radiance
+    }
+
+    /// Traces one path through a random point in each pixel and adds it to
+    /// the pixel's samples. Each `pass` uses different random numbers.
+    pub fn render_pass(&self, world: &World, camera: &Camera, pass: u32, canvas: &mut Canvas) {
+        TileRenderer::new().render_tiles(
+            camera.hsize(),
+            camera.vsize(),
+            |xy| {
+                let x = xy.x() as u32;
+                let y = xy.y() as u32;
+                let mut rng = rng_for_pixel(x, y, FIRST_PASS_STREAM + pass as u64);
+                let offset = pt2(rng.gen(), rng.gen());
+                let lens_sample = pt2(rng.gen(), rng.gen());
+                let time = camera.shutter_time(rng.gen());
+                let ray = camera
+                    .ray_for_pixel_through_lens(x, y, offset, lens_sample)
+                    .with_time(time);
+                self.radiance(world, ray, &mut rng)
+            },
+            |tile| canvas.add_tile(&tile),
+        );
+    }
+
+    pub fn render(&self, world: &World, camera: &Camera, num_passes: u32) -> Canvas {
+        let mut canvas = Canvas::new(camera.hsize(), camera.vsize());
+        for pass in 0..num_passes {
+            self.render_pass(world, camera, pass, &mut canvas);
+        }
+        canvas
+    }
+}
+
+impl Default for PathTracer {
+    fn default() -> Self {
+        PathTracer::new()
+    }
+}
+
+/// The light arriving directly from every light in the world, weighted by the
+/// cosine of its angle to the normal the way `lighting`'s diffuse term is.
+fn direct_irradiance(world: &World, comps: &Computations) -> Rgb {
+    let mut total = Rgb::black();
+    world.for_each_light(None, comps.time, |light| {
+        let light_intensity = light.intensity_at(comps.over_point, comps.time, world);
+        if light_intensity <= 0.0 {
+            return;
+        }
+        let samples = light.samples(comps.over_point);
+        let irradiance = samples.iter().fold(Rgb::black(), |irradiance, sample| {
+            let cosine = sample.direction.dot(comps.normalv);
+            if cosine <= 0.0 {
+                return irradiance;
+            }
+            irradiance + light.intensity() * (cosine * light.strength(sample))
+        });
+        total = total + irradiance * (light_intensity / samples.len().max(1) as f32);
+    });
+    total
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{pt3, vec3, Background, Material, PointLight, Shape, Transform};
+    use super::*;
+    use std::f32::consts::PI;
+
+    fn assert_close(actual: Rgb, expected: f32, tolerance: f32) {
+        for component in actual.components() {
+            assert!((component - expected).abs() < tolerance, "{actual:?}");
+        }
+    }
+
+    /// A camera inside a glowing sphere. Every bounce adds the emission again,
+    /// scaled by the albedo, so the radiance converges to
+    /// `emission / (1 - albedo)`.
+    fn furnace(emission: f32, albedo: f32) -> World {
+        let sphere = Shape::sphere()
+            .with_transform(Transform::scaling(10.0, 10.0, 10.0))
+            .with_material(Material {
+                color: Rgb::new(1.0, 1.0, 1.0),
+                diffuse: albedo,
+                emission: Rgb::new(emission, emission, emission),
+                ..Material::default()
+            });
+        World {
+            objects: vec![sphere],
+            lights: vec![],
+            ..World::default()
+        }
+    }
+
+    #[test]
+    fn an_empty_world_is_black() {
+        let world = World::new();
+        let ray = Ray::new(pt3(0.0, 0.0, 0.0), vec3(0.0, 0.0, 1.0));
+        let mut rng = rng_for_pixel(0, 0, 0);
+        assert_eq!(
+            PathTracer::new().radiance(&world, ray, &mut rng),
+            Rgb::black()
+        );
+    }
+
+    #[test]
+    fn paths_that_escape_pick_up_the_background() {
+        let floor = Shape::plane().with_material(Material {
+            color: Rgb::new(0.5, 0.5, 0.5),
+            ..Material::default()
+        });
+        let world = World {
+            objects: vec![floor],
+            background: Background::Constant(Rgb::new(1.0, 1.0, 1.0)),
+            ..World::default()
+        };
+        // Every bounce off the floor escapes into the sky.
+        let ray = Ray::new(pt3(0.0, 1.0, 0.0), vec3(0.0, -1.0, 0.0));
+        let mut rng = rng_for_pixel(0, 0, 0);
+        let radiance = PathTracer::new().radiance(&world, ray, &mut rng);
+        assert_eq!(radiance, Rgb::new(0.45, 0.45, 0.45));
+    }
+
+    #[test]
+    fn direct_light_matches_the_diffuse_term_of_lighting() {
+        let floor = Shape::plane().with_material(Material {
+            color: Rgb::new(1.0, 0.5, 0.25),
+            ..Material::default()
+        });
+        let light = PointLight::new(pt3(0.0, 10.0, 0.0), Rgb::new(1.0, 1.0, 1.0));
+        let world = World {
+            objects: vec![floor],
+            lights: vec![Box::new(light)],
+            ..World::default()
+        };
+        // Everything that bounces off the floor escapes, so there's no noise.
+        let ray = Ray::new(pt3(0.0, 1.0, 0.0), vec3(0.0, -1.0, 0.0));
+        let mut rng = rng_for_pixel(0, 0, 0);
+        let radiance = PathTracer::new().radiance(&world, ray, &mut rng);
+        assert_eq!(radiance, Rgb::new(0.9, 0.45, 0.225));
+    }
+
+    #[test]
+    fn a_furnace_converges_to_the_sum_of_every_bounce() {
+        let world = furnace(0.5, 0.5);
+        let tracer = PathTracer::new().with_max_bounces(64);
+        let mut rng = rng_for_pixel(0, 0, 0);
+        let num_paths = 2000;
+        let total = (0..num_paths).fold(Rgb::black(), |total, _| {
+            let ray = Ray::new(pt3(0.0, 0.0, 0.0), vec3(0.0, 0.0, 1.0));
+            total + tracer.radiance(&world, ray, &mut rng)
+        });
+        assert_close(total * (1.0 / num_paths as f32), 1.0, 0.05);
+    }
+
+    #[test]
+    fn max_bounces_limits_the_path_length() {
+        let world = furnace(1.0, 0.5);
+        let tracer = PathTracer::new().with_max_bounces(1).with_min_bounces(2);
+        let ray = Ray::new(pt3(0.0, 0.0, 0.0), vec3(0.0, 0.0, 1.0));
+        let mut rng = rng_for_pixel(0, 0, 0);
+        // The emission seen directly, plus the light arriving directly at the
+        // two points the path touches, halved by the albedo at each.
+        assert_close(tracer.radiance(&world, ray, &mut rng), 1.75, 0.001);
+    }
+
+    #[test]
+    fn passes_accumulate_into_the_canvas() {
+        let world = furnace(0.5, 0.5);
+        let camera = Camera::new(4, 4, PI / 2.0);
+        let tracer = PathTracer::new();
+        let canvas = tracer.render(&world, &camera, 128);
+        for pixel in canvas.iter_pixels() {
+            assert_close(pixel.rgb, 1.0, 0.15);
+        }
+
+        let again = tracer.render(&world, &camera, 128);
+        let rgbs = |canvas: &Canvas| {
+            canvas
+                .iter_pixels()
+                .map(|pixel| pixel.rgb)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(rgbs(&again), rgbs(&canvas));
+    }
+
+    #[test]
+    fn emissive_spheres_light_the_scene_and_are_seen_directly() {
+        let glow = Shape::sphere()
+            .with_transform(Transform::translation(0.0, 4.0, 0.0))
+            .with_material(Material {
+                emission: Rgb::new(16.0, 16.0, 16.0),
+                ..Material::default()
+            });
+        let floor = Shape::plane().with_material(Material {
+            diffuse: 1.0,
+            ..Material::default()
+        });
+        let world = World {
+            objects: vec![glow, floor],
+            lights: vec![],
+            ..World::default()
+        };
+        let tracer = PathTracer::new();
+        let mut rng = rng_for_pixel(0, 0, 0);
+
+        let at_glow = Ray::new(pt3(0.0, 4.0, -5.0), vec3(0.0, 0.0, 1.0));
+        let seen = tracer.radiance(&world, at_glow, &mut rng);
+        assert!(seen.red() >= 16.0, "{seen:?}");
+
+        // The floor below is lit to 16 * (1 / 4)², give or take the noise
+        // from sampling the sphere and the light bouncing back off it.
+        let at_floor = Ray::new(pt3(0.0, 1.0, 0.0), vec3(0.0, -1.0, 0.0));
+        let num_paths = 256;
+        let total = (0..num_paths).fold(Rgb::black(), |total, _| {
+            total + tracer.radiance(&world, at_floor, &mut rng)
+        });
+        assert_close(total * (1.0 / num_paths as f32), 1.0, 0.1);
+    }
+}
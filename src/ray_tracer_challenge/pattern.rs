@@ -0,0 +1,246 @@
+use super::{
+    CubeMap, Noise, Point3, Rgb, Shape, Texture, TextureMap, Transform, UvMapping, UvPattern,
+};
+
+/// What a pattern looks like in its own pattern space.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PatternKind {
+    /// Six images wrapped around the cube from -1.0 to 1.0 on every axis.
+    CubeMap(CubeMap),
+    /// A flat pattern wrapped around the shape.
+    TextureMap(TextureMap),
+    /// Veins of `b` through `a`, running across the x axis. See `Noise::marble`.
+    Marble { a: Rgb, b: Rgb, noise: Noise },
+    /// Rings of `a` fading to `b` around the y axis. See `Noise::wood`.
+    Wood { a: Rgb, b: Rgb, noise: Noise },
+    /// Wisps of `b` over `a`. See `Noise::clouds`.
+    Clouds { a: Rgb, b: Rgb, noise: Noise },
+    /// Another pattern, with each lookup nudged up to `scale` units in a
+    /// direction given by `noise`, which makes straight lines wobble.
+    Perturbed {
+        pattern: Box<Pattern>,
+        noise: Noise,
+        scale: f32,
+    },
+}
+
+/// Varies a material's color across a shape's surface. Patterns are
+/// transformed relative to the shape they're on, so they move with it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pattern {
+    pub kind: PatternKind,
+    transform: Transform,
+    inverse_transform: Transform,
+}
+
+impl Pattern {
+    pub fn new(kind: PatternKind) -> Pattern {
+        Pattern {
+            kind,
+            transform: Transform::identity(),
+            inverse_transform: Transform::identity(),
+        }
+    }
+
+    pub fn cube_map(cube_map: CubeMap) -> Pattern {
+        Pattern::new(PatternKind::CubeMap(cube_map))
+    }
+
+    pub fn texture_map(uv_pattern: UvPattern, mapping: UvMapping) -> Pattern {
+        Pattern::new(PatternKind::TextureMap(TextureMap::new(
+            uv_pattern, mapping,
+        )))
+    }
+
+    /// An image wrapped around the shape. See `Texture::load`.
+    pub fn image(texture: Texture, mapping: UvMapping) -> Pattern {
+        Pattern::texture_map(UvPattern::Image(texture), mapping)
+    }
+
+    pub fn marble(a: Rgb, b: Rgb, noise: Noise) -> Pattern {
+        Pattern::new(PatternKind::Marble { a, b, noise })
+    }
+
+    pub fn wood(a: Rgb, b: Rgb, noise: Noise) -> Pattern {
+        Pattern::new(PatternKind::Wood { a, b, noise })
+    }
+
+    pub fn clouds(a: Rgb, b: Rgb, noise: Noise) -> Pattern {
+        Pattern::new(PatternKind::Clouds { a, b, noise })
+    }
+
+    pub fn perturbed(pattern: Pattern, noise: Noise, scale: f32) -> Pattern {
+        Pattern::new(PatternKind::Perturbed {
+            pattern: Box::new(pattern),
+            noise,
+            scale,
+        })
+    }
+
+    pub fn with_transform(mut self, transform: Transform) -> Pattern {
+        self.set_transform(transform);
+        self
+    }
+
+    pub fn transform(&self) -> Transform {
+        self.transform
+    }
+
+    pub fn set_transform(&mut self, transform: Transform) {
+        self.transform = transform;
+        self.inverse_transform = transform.inverse();
+    }
+
+    /// The color at `pattern_point`, in pattern space.
+    pub fn color_at(&self, pattern_point: Point3) -> Rgb {
+        match &self.kind {
+            PatternKind::CubeMap(cube_map) => cube_map.color_at(pattern_point),
+            PatternKind::TextureMap(texture_map) => texture_map.color_at(pattern_point),
+            PatternKind::Marble { a, b, noise } => blend(*a, *b, noise.marble(pattern_point)),
+            PatternKind::Wood { a, b, noise } => blend(*a, *b, noise.wood(pattern_point)),
+            PatternKind::Clouds { a, b, noise } => blend(*a, *b, noise.clouds(pattern_point)),
+            PatternKind::Perturbed {
+                pattern,
+                noise,
+                scale,
+            } => {
+                let nudged = pattern_point + noise.vector(pattern_point) * *scale;
+                pattern.color_at(pattern.inverse_transform * nudged)
+            }
+        }
+    }
+
+    /// The color at `world_point` on `object`, wherever it was at `time`.
+    pub fn color_at_shape(&self, object: &Shape, world_point: Point3, time: f32) -> Rgb {
+        let object_point = object.world_to_object(world_point, time);
+        self.color_at(self.inverse_transform * object_point)
+    }
+}
+
+fn blend(a: Rgb, b: Rgb, t: f32) -> Rgb {
+    a * (1.0 - t) + b * t
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{pt3, Texture};
+    use super::*;
+
+    fn colored_faces() -> CubeMap {
+        CubeMap::new(
+            [
+                Rgb::new(1.0, 0.0, 0.0),
+                Rgb::new(0.0, 1.0, 0.0),
+                Rgb::new(0.0, 0.0, 1.0),
+                Rgb::new(1.0, 1.0, 0.0),
+                Rgb::new(0.0, 1.0, 1.0),
+                Rgb::new(1.0, 0.0, 1.0),
+            ]
+            .map(|rgb| UvPattern::Image(Texture::solid(rgb))),
+        )
+    }
+
+    #[test]
+    fn a_pattern_with_an_object_transformation() {
+        let object = Shape::cube().with_transform(Transform::scaling(2.0, 2.0, 2.0));
+        let pattern = Pattern::cube_map(colored_faces());
+        let color = pattern.color_at_shape(&object, pt3(2.0, 0.5, 0.5), 0.0);
+        assert_eq!(color, Rgb::new(0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn a_pattern_with_a_pattern_transformation() {
+        let object = Shape::cube();
+        let pattern = Pattern::cube_map(colored_faces())
+            .with_transform(Transform::rotation_y(std::f32::consts::PI));
+        // Turned around, the right face is where the left face was.
+        let color = pattern.color_at_shape(&object, pt3(-1.0, 0.5, 0.5), 0.0);
+        assert_eq!(color, Rgb::new(0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn perturbing_a_pattern_nudges_its_lookups() {
+        let checkers = || {
+            Pattern::texture_map(
+                UvPattern::checkers(2.0, 2.0, Rgb::black(), Rgb::new(1.0, 1.0, 1.0)),
+                UvMapping::Planar,
+            )
+        };
+        let unperturbed = Pattern::perturbed(checkers(), Noise::new(1), 0.0);
+        let perturbed = Pattern::perturbed(checkers(), Noise::new(1), 0.5);
+        let points: Vec<Point3> = (0..100)
+            .map(|i| pt3(i as f32 * 0.13, 0.0, i as f32 * 0.07))
+            .collect();
+        for &point in &points {
+            assert_eq!(unperturbed.color_at(point), checkers().color_at(point));
+        }
+        assert!(points
+            .iter()
+            .any(|&point| perturbed.color_at(point) != checkers().color_at(point)));
+    }
+
+    #[test]
+    fn procedural_patterns_blend_between_their_two_colors() {
+        let a = Rgb::new(1.0, 0.0, 0.0);
+        let b = Rgb::new(0.0, 0.0, 1.0);
+        let noise = Noise::new(2);
+        let patterns = [
+            Pattern::marble(a, b, noise.clone()),
+            Pattern::wood(a, b, noise.clone()),
+            Pattern::clouds(a, b, noise),
+        ];
+        for pattern in patterns {
+            for i in 0..50 {
+                let color = pattern.color_at(pt3(i as f32 * 0.31, 0.2, i as f32 * -0.17));
+                assert!((color.red() + color.blue() - 1.0).abs() < 0.0001);
+                assert_eq!(color.green(), 0.0);
+            }
+        }
+    }
+
+    #[test]
+    fn procedural_patterns_match_golden_image() {
+        use super::super::{Material, PointLight, World};
+        use crate::library::image_comparison::assert_scene_matches_golden_image;
+        use camino::Utf8Path;
+
+        let marble = Pattern::marble(
+            Rgb::new(0.9, 0.9, 0.85),
+            Rgb::new(0.2, 0.25, 0.3),
+            Noise::new(11),
+        )
+        .with_transform(Transform::scaling(0.5, 0.5, 0.5));
+        let sphere = Shape::sphere().with_material(Material {
+            pattern: Some(marble),
+            ..Material::default()
+        });
+        let wood = Pattern::wood(
+            Rgb::new(0.6, 0.4, 0.2),
+            Rgb::new(0.35, 0.2, 0.1),
+            Noise::new(12),
+        )
+        .with_transform(Transform::scaling(0.3, 0.3, 0.3));
+        let floor = Shape::plane()
+            .with_transform(Transform::translation(0.0, -1.0, 0.0))
+            .with_material(Material {
+                pattern: Some(wood),
+                specular: 0.0,
+                ..Material::default()
+            });
+        let world = World {
+            objects: vec![sphere, floor],
+            lights: vec![Box::new(PointLight::new(
+                pt3(-10.0, 10.0, -10.0),
+                Rgb::new(1.0, 1.0, 1.0),
+            ))],
+            ..World::default()
+        };
+
+        assert_scene_matches_golden_image(
+            |ray| world.color_at(ray),
+            pt3(0.0, 1.5, -4.0),
+            pt3(0.0, -0.25, 0.0),
+            Utf8Path::new("src/tests/procedural_patterns_expected_output.png"),
+        );
+    }
+}
@@ -0,0 +1,83 @@
+use super::{Point3, Transform, Vec3};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ray {
+    pub origin: Point3,
+    pub direction: Vec3,
+    /// When the ray was cast, somewhere between the camera's `shutter_open`
+    /// and `shutter_close`. Moving shapes are intersected where they were then.
+    pub time: f32,
+}
+
+impl Ray {
+    pub fn new(origin: Point3, direction: Vec3) -> Ray {
+        Ray {
+            origin,
+            direction,
+            time: 0.0,
+        }
+    }
+    pub fn with_time(mut self, time: f32) -> Ray {
+        self.time = time;
+        self
+    }
+    /// The point `t` units along the ray. `t` is in multiples of the
+    /// direction's length, so it's only a distance if the direction is normalized.
+    pub fn position(&self, t: f32) -> Point3 {
+        self.origin + self.direction * t
+    }
+    pub fn transform(&self, transform: Transform) -> Ray {
+        Ray {
+            origin: transform * self.origin,
+            direction: transform * self.direction,
+            time: self.time,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{pt3, vec3};
+    use super::*;
+
+    #[test]
+    fn creating_and_querying_a_ray() {
+        let origin = pt3(1.0, 2.0, 3.0);
+        let direction = vec3(4.0, 5.0, 6.0);
+        let ray = Ray::new(origin, direction);
+        assert_eq!(ray.origin, origin);
+        assert_eq!(ray.direction, direction);
+    }
+
+    #[test]
+    fn computing_a_point_from_a_distance() {
+        let ray = Ray::new(pt3(2.0, 3.0, 4.0), vec3(1.0, 0.0, 0.0));
+        assert_eq!(ray.position(0.0), pt3(2.0, 3.0, 4.0));
+        assert_eq!(ray.position(1.0), pt3(3.0, 3.0, 4.0));
+        assert_eq!(ray.position(-1.0), pt3(1.0, 3.0, 4.0));
+        assert_eq!(ray.position(2.5), pt3(4.5, 3.0, 4.0));
+    }
+
+    #[test]
+    fn translating_a_ray() {
+        let ray = Ray::new(pt3(1.0, 2.0, 3.0), vec3(0.0, 1.0, 0.0));
+        let transformed = ray.transform(Transform::translation(3.0, 4.0, 5.0));
+        assert_eq!(transformed.origin, pt3(4.0, 6.0, 8.0));
+        assert_eq!(transformed.direction, vec3(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn scaling_a_ray() {
+        let ray = Ray::new(pt3(1.0, 2.0, 3.0), vec3(0.0, 1.0, 0.0));
+        let transformed = ray.transform(Transform::scaling(2.0, 3.0, 4.0));
+        assert_eq!(transformed.origin, pt3(2.0, 6.0, 12.0));
+        assert_eq!(transformed.direction, vec3(0.0, 3.0, 0.0));
+    }
+
+    #[test]
+    fn transforming_a_ray_keeps_its_time() {
+        let ray = Ray::new(pt3(1.0, 2.0, 3.0), vec3(0.0, 1.0, 0.0)).with_time(0.25);
+        let transformed = ray.transform(Transform::translation(3.0, 4.0, 5.0));
+        assert_eq!(transformed.time, 0.25);
+    }
+}
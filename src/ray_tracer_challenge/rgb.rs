@@ -1,4 +1,6 @@
 use crate::extensions::F32Extension;
+use palette::color_difference::EuclideanDistance;
+use palette::FromColor;
 use std::ops::{Add, Mul, Sub};
 
 type PaletteSrgb = palette::Srgb<f32>;
@@ -31,6 +33,18 @@ impl Rgb {
     pub fn components(&self) -> [f32; 3] {
         [self.red(), self.green(), self.blue()]
     }
+    /// Clamps each component to 0.0 to 1.0.
+    pub fn clamp(&self) -> Rgb {
+        let [red, green, blue] = self.components().map(|c| c.clamp(0.0, 1.0));
+        Rgb::new(red, green, blue)
+    }
+    /// Euclidean distance in Oklab, which is perceptually uniform. Roughly 0.02
+    /// is a just-noticeable difference.
+    pub fn oklab_distance(&self, other: Rgb) -> f32 {
+        let a = palette::Oklab::from_color(self.palette_srgb);
+        let b = palette::Oklab::from_color(other.palette_srgb);
+        a.distance(b)
+    }
 }
 
 impl Add<Rgb> for Rgb {
@@ -91,15 +105,15 @@ impl From<&[u8]> for Rgb {
     }
 }
 
-impl Into<PaletteSrgb> for Rgb {
-    fn into(self) -> PaletteSrgb {
-        self.palette_srgb
+impl From<Rgb> for PaletteSrgb {
+    fn from(rgb: Rgb) -> PaletteSrgb {
+        rgb.palette_srgb
     }
 }
 
-impl Into<palette::Srgba<f32>> for Rgb {
-    fn into(self) -> palette::Srgba<f32> {
-        palette::Srgba::<f32>::new(self.red(), self.green(), self.blue(), 1.0)
+impl From<Rgb> for palette::Srgba<f32> {
+    fn from(rgb: Rgb) -> palette::Srgba<f32> {
+        palette::Srgba::<f32>::new(rgb.red(), rgb.green(), rgb.blue(), 1.0)
     }
 }
 
@@ -153,4 +167,18 @@ mod tests {
         let expected = Rgb::new(0.9, 0.2, 0.04);
         assert_eq!(actual, expected);
     }
+
+    #[test]
+    fn clamping_colors() {
+        let rgb = Rgb::new(-0.5, 0.4, 1.7);
+        assert_eq!(rgb.clamp(), Rgb::new(0.0, 0.4, 1.0));
+    }
+
+    #[test]
+    fn oklab_distance() {
+        let black = Rgb::black();
+        let white = Rgb::new(1.0, 1.0, 1.0);
+        assert!(black.oklab_distance(black).almost_eq(0.0));
+        assert!(black.oklab_distance(white).almost_eq(1.0));
+    }
 }
@@ -0,0 +1,390 @@
+use rand::{Rng, SeedableRng};
+use rand_pcg::Pcg32;
+
+use super::{pt2, vec3, Point2, Point3, Vec3};
+
+/// Where samples are placed within a pixel.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SamplePattern {
+    /// Evenly spaced, in a square grid.
+    Regular,
+    /// One random sample in each cell of a square grid.
+    Jittered,
+    /// The Halton sequence in bases 2 and 3, randomly shifted per pixel.
+    Halton,
+    /// The first two dimensions of the Sobol sequence, randomly scrambled per pixel.
+    Sobol,
+}
+
+/// How samples are weighted when they're combined into a pixel.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReconstructionFilter {
+    /// Every sample in the pixel counts equally.
+    Box,
+    /// Samples are spread `radius` pixels out from the pixel's center, and
+    /// weighted by a Gaussian that falls to zero at `radius`. Larger values of
+    /// `alpha` fall off faster, for a sharper result.
+    Gaussian { radius: f32, alpha: f32 },
+}
+
+impl ReconstructionFilter {
+    pub fn gaussian() -> ReconstructionFilter {
+        ReconstructionFilter::Gaussian {
+            radius: 1.0,
+            alpha: 2.0,
+        }
+    }
+
+    /// How far from the pixel's center samples are placed, in pixels.
+    pub fn radius(&self) -> f32 {
+        match self {
+            ReconstructionFilter::Box => 0.5,
+            ReconstructionFilter::Gaussian { radius, .. } => *radius,
+        }
+    }
+
+    /// `offset` is relative to the pixel's center, in pixels.
+    pub fn weight(&self, offset: Point2) -> f32 {
+        match self {
+            ReconstructionFilter::Box => 1.0,
+            ReconstructionFilter::Gaussian { radius, alpha } => {
+                let gaussian = |d: f32| {
+                    let weight = (-alpha * d * d).exp() - (-alpha * radius * radius).exp();
+                    weight.max(0.0)
+                };
+                gaussian(offset.x()) * gaussian(offset.y())
+            }
+        }
+    }
+}
+
+/// How many samples a camera takes per pixel, where it takes them, and how
+/// they're combined.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sampling {
+    pub pattern: SamplePattern,
+    /// Regular and jittered patterns round this up to a square number.
+    pub samples_per_pixel: u32,
+    pub filter: ReconstructionFilter,
+}
+
+/// One sample within a pixel.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PixelSample {
+    /// Relative to the pixel's top left corner, in pixels. The pixel's center
+    /// is at `(0.5, 0.5)`, and filters wider than a pixel place samples
+    /// outside of 0.0 to 1.0.
+    pub offset: Point2,
+    pub weight: f32,
+}
+
+impl Sampling {
+    /// A single sample through the center of each pixel.
+    pub fn single() -> Sampling {
+        Sampling {
+            pattern: SamplePattern::Regular,
+            samples_per_pixel: 1,
+            filter: ReconstructionFilter::Box,
+        }
+    }
+
+    pub fn new(
+        pattern: SamplePattern,
+        samples_per_pixel: u32,
+        filter: ReconstructionFilter,
+    ) -> Sampling {
+        Sampling {
+            pattern,
+            samples_per_pixel: samples_per_pixel.max(1),
+            filter,
+        }
+    }
+
+    /// Returns the same samples every time it's called with the same pixel.
+    pub fn samples_for_pixel(&self, x: u32, y: u32) -> Vec<PixelSample> {
+        let mut rng = rng_for_pixel(x, y, 0);
+        let unit_square_points = match self.pattern {
+            SamplePattern::Regular => grid(self.grid_size(), || 0.5),
+            SamplePattern::Jittered => grid(self.grid_size(), || rng.gen()),
+            SamplePattern::Halton => {
+                let shift = pt2(rng.gen(), rng.gen());
+                (0..self.samples_per_pixel)
+                    .map(|i| {
+                        let x = (radical_inverse(i, 2) + shift.x()).fract();
+                        let y = (radical_inverse(i, 3) + shift.y()).fract();
+                        pt2(x, y)
+                    })
+                    .collect()
+            }
+            SamplePattern::Sobol => {
+                let scramble: (u32, u32) = (rng.gen(), rng.gen());
+                (0..self.samples_per_pixel)
+                    .map(|i| {
+                        let (x, y) = sobol_2d(i, scramble);
+                        pt2(x, y)
+                    })
+                    .collect()
+            }
+        };
+
+        let radius = self.filter.radius();
+        unit_square_points
+            .into_iter()
+            .map(|point| {
+                let from_center = pt2(
+                    (point.x() - 0.5) * 2.0 * radius,
+                    (point.y() - 0.5) * 2.0 * radius,
+                );
+                PixelSample {
+                    offset: pt2(from_center.x() + 0.5, from_center.y() + 0.5),
+                    weight: self.filter.weight(from_center),
+                }
+            })
+            .collect()
+    }
+
+    fn grid_size(&self) -> u32 {
+        (self.samples_per_pixel as f32).sqrt().ceil() as u32
+    }
+}
+
+impl Default for Sampling {
+    fn default() -> Self {
+        Sampling::single()
+    }
+}
+
+/// A random number generator that's seeded from a pixel's coordinates, so
+/// renders come out the same every time and on any number of threads. Use a
+/// different `stream` for each independent use within the same pixel.
+pub fn rng_for_pixel(x: u32, y: u32, stream: u64) -> Pcg32 {
+    let seed = (x as u64) << 32 | y as u64;
+    Pcg32::new(seed, stream)
+}
+
+/// Like `rng_for_pixel`, but seeded from a point in space, for sampling that
+/// happens while shading a surface point rather than a pixel.
+pub fn rng_for_point(point: Point3, stream: u64) -> Pcg32 {
+    let [x, y, z] = [point.x(), point.y(), point.z()].map(|c| c.to_bits() as u64);
+    let seed = x ^ y.rotate_left(21) ^ z.rotate_left(42);
+    Pcg32::new(seed, stream)
+}
+
+/// Maps a point in the unit square to a direction in the hemisphere around
+/// `normal`, with directions near the normal more likely in proportion to the
+/// cosine of their angle from it. That matches how much light a matte surface
+/// receives from each direction, so fewer samples are wasted.
+pub fn cosine_weighted_hemisphere(normal: Vec3, u: f32, v: f32) -> Vec3 {
+    let radius = u.sqrt();
+    let angle = std::f32::consts::TAU * v;
+    let x = radius * angle.cos();
+    let y = radius * angle.sin();
+    let z = (1.0 - u).max(0.0).sqrt();
+
+    let (tangent, bitangent) = normal.perpendiculars();
+    (tangent * x + bitangent * y + normal * z).normalize()
+}
+
+/// One point per cell of a `size` by `size` grid over the unit square.
+/// `position_in_cell` picks where in the cell, from 0.0 to 1.0, for each axis.
+fn grid(size: u32, mut position_in_cell: impl FnMut() -> f32) -> Vec<Point2> {
+    let cell_size = 1.0 / size as f32;
+    let mut points = Vec::with_capacity((size * size) as usize);
+    for row in 0..size {
+        for column in 0..size {
+            let x = (column as f32 + position_in_cell()) * cell_size;
+            let y = (row as f32 + position_in_cell()) * cell_size;
+            points.push(pt2(x, y));
+        }
+    }
+    points
+}
+
+/// Mirrors the digits of `index` in `base` around the decimal point, so
+/// 1, 2, 3 in base 2 become 0.5, 0.25, 0.75.
+pub fn radical_inverse(mut index: u32, base: u32) -> f32 {
+    let inverse_base = 1.0 / base as f32;
+    let mut fraction = inverse_base;
+    let mut result = 0.0;
+    while index > 0 {
+        result += (index % base) as f32 * fraction;
+        index /= base;
+        fraction *= inverse_base;
+    }
+    result
+}
+
+/// The `index`th point of the 2D Sobol sequence, with each dimension's bits
+/// XORed with `scramble`.
+pub fn sobol_2d(index: u32, scramble: (u32, u32)) -> (f32, f32) {
+    // The first dimension's direction numbers are powers of two, which makes
+    // it the base 2 radical inverse. The second's are the rows of Pascal's
+    // triangle mod 2, each of which is the previous XOR itself shifted right.
+    let mut x = scramble.0;
+    let mut y = scramble.1;
+    let mut direction_x = 1 << 31;
+    let mut direction_y = 1 << 31;
+    let mut index = index;
+    while index > 0 {
+        if index & 1 == 1 {
+            x ^= direction_x;
+            y ^= direction_y;
+        }
+        index >>= 1;
+        direction_x >>= 1;
+        direction_y ^= direction_y >> 1;
+    }
+    let to_unit = |bits: u32| (bits >> 8) as f32 / (1 << 24) as f32;
+    (to_unit(x), to_unit(y))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::extensions::F32Extension;
+
+    #[test]
+    fn a_single_sample_goes_through_the_center_of_the_pixel() {
+        let samples = Sampling::single().samples_for_pixel(3, 4);
+        assert_eq!(
+            samples,
+            vec![PixelSample {
+                offset: pt2(0.5, 0.5),
+                weight: 1.0,
+            }]
+        );
+    }
+
+    #[test]
+    fn regular_samples_are_evenly_spaced() {
+        let sampling = Sampling::new(SamplePattern::Regular, 4, ReconstructionFilter::Box);
+        let offsets: Vec<Point2> = sampling
+            .samples_for_pixel(0, 0)
+            .iter()
+            .map(|sample| sample.offset)
+            .collect();
+        assert_eq!(
+            offsets,
+            vec![
+                pt2(0.25, 0.25),
+                pt2(0.75, 0.25),
+                pt2(0.25, 0.75),
+                pt2(0.75, 0.75)
+            ]
+        );
+    }
+
+    #[test]
+    fn regular_and_jittered_patterns_round_up_to_a_square_number() {
+        let sampling = Sampling::new(SamplePattern::Jittered, 5, ReconstructionFilter::Box);
+        assert_eq!(sampling.samples_for_pixel(0, 0).len(), 9);
+    }
+
+    #[test]
+    fn jittered_samples_fall_in_their_own_cell() {
+        let sampling = Sampling::new(SamplePattern::Jittered, 16, ReconstructionFilter::Box);
+        let samples = sampling.samples_for_pixel(7, 11);
+        for (index, sample) in samples.iter().enumerate() {
+            let column = (index % 4) as f32;
+            let row = (index / 4) as f32;
+            assert!(
+                sample.offset.x() >= column * 0.25 && sample.offset.x() < (column + 1.0) * 0.25
+            );
+            assert!(sample.offset.y() >= row * 0.25 && sample.offset.y() < (row + 1.0) * 0.25);
+        }
+    }
+
+    #[test]
+    fn random_patterns_are_the_same_for_the_same_pixel() {
+        for pattern in [
+            SamplePattern::Jittered,
+            SamplePattern::Halton,
+            SamplePattern::Sobol,
+        ] {
+            let sampling = Sampling::new(pattern, 16, ReconstructionFilter::Box);
+            assert_eq!(
+                sampling.samples_for_pixel(2, 3),
+                sampling.samples_for_pixel(2, 3)
+            );
+            assert_ne!(
+                sampling.samples_for_pixel(2, 3),
+                sampling.samples_for_pixel(3, 2)
+            );
+        }
+    }
+
+    #[test]
+    fn samples_stay_inside_the_pixel_with_a_box_filter() {
+        for pattern in [
+            SamplePattern::Jittered,
+            SamplePattern::Halton,
+            SamplePattern::Sobol,
+        ] {
+            let sampling = Sampling::new(pattern, 64, ReconstructionFilter::Box);
+            for sample in sampling.samples_for_pixel(5, 5) {
+                assert!((0.0..1.0).contains(&sample.offset.x()));
+                assert!((0.0..1.0).contains(&sample.offset.y()));
+            }
+        }
+    }
+
+    #[test]
+    fn the_halton_sequence() {
+        let base_2: Vec<f32> = (0..5).map(|i| radical_inverse(i, 2)).collect();
+        assert_eq!(base_2, vec![0.0, 0.5, 0.25, 0.75, 0.125]);
+        let base_3: Vec<f32> = (0..4).map(|i| radical_inverse(i, 3)).collect();
+        assert!(base_3[1].almost_eq(1.0 / 3.0));
+        assert!(base_3[2].almost_eq(2.0 / 3.0));
+        assert!(base_3[3].almost_eq(1.0 / 9.0));
+    }
+
+    #[test]
+    fn the_sobol_sequence() {
+        let points: Vec<(f32, f32)> = (0..4).map(|i| sobol_2d(i, (0, 0))).collect();
+        assert_eq!(
+            points,
+            vec![(0.0, 0.0), (0.5, 0.5), (0.25, 0.75), (0.75, 0.25)]
+        );
+    }
+
+    #[test]
+    fn the_gaussian_filter_favors_samples_near_the_center() {
+        let filter = ReconstructionFilter::gaussian();
+        let center = filter.weight(pt2(0.0, 0.0));
+        let nearby = filter.weight(pt2(0.25, 0.0));
+        let edge = filter.weight(pt2(1.0, 0.0));
+        assert!(center > nearby);
+        assert!(nearby > edge);
+        assert!(edge.almost_eq(0.0));
+    }
+
+    #[test]
+    fn the_gaussian_filter_spreads_samples_past_the_pixel() {
+        let sampling = Sampling::new(SamplePattern::Regular, 4, ReconstructionFilter::gaussian());
+        let offsets: Vec<Point2> = sampling
+            .samples_for_pixel(0, 0)
+            .iter()
+            .map(|sample| sample.offset)
+            .collect();
+        assert_eq!(offsets[0], pt2(0.0, 0.0));
+        assert_eq!(offsets[3], pt2(1.0, 1.0));
+    }
+
+    #[test]
+    fn cosine_weighted_directions_stay_in_the_hemisphere() {
+        let mut rng = rng_for_pixel(0, 0, 0);
+        let normal = vec3(0.0, 1.0, 1.0).normalize();
+        let mut total_cosine = 0.0;
+        let num_samples = 4096;
+        for _ in 0..num_samples {
+            let direction = cosine_weighted_hemisphere(normal, rng.gen(), rng.gen());
+            assert!(direction.length().almost_eq(1.0));
+            let cosine = direction.dot(normal);
+            assert!(cosine >= 0.0);
+            total_cosine += cosine;
+        }
+        // The average cosine of a cosine-weighted hemisphere is 2/3.
+        let mean_cosine = total_cosine / num_samples as f32;
+        assert!((mean_cosine - 2.0 / 3.0).abs() < 0.02, "{mean_cosine}");
+    }
+}
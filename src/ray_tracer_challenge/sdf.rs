@@ -0,0 +1,419 @@
+//! Signed distance fields: functions giving the distance from a point to the
+//! nearest surface, negative inside. They're cheap to blend and repeat in
+//! ways that are hard with `Shape`s, and are rendered by marching rays
+//! through them with a `SphereTracer`.
+//!
+//! Primitives are centered on the origin. To move one, transform the point
+//! by the inverse of where it should go: `sphere(transform.inverse() * p, 1.0)`.
+
+use super::{lighting, pt3, vec3, Background, Light, Material, Point3, PointLight, Ray, Rgb, Vec3};
+
+pub fn sphere(point: Point3, radius: f32) -> f32 {
+    (point - Point3::new(0.0, 0.0, 0.0)).length() - radius
+}
+
+/// A box reaching `half_size` from the origin along each axis.
+pub fn cuboid(point: Point3, half_size: Vec3) -> f32 {
+    let q = vec3(
+        point.x().abs() - half_size.x(),
+        point.y().abs() - half_size.y(),
+        point.z().abs() - half_size.z(),
+    );
+    let outside = vec3(q.x().max(0.0), q.y().max(0.0), q.z().max(0.0)).length();
+    let inside = q.x().max(q.y()).max(q.z()).min(0.0);
+    outside + inside
+}
+
+/// A ring in the xz plane, `major_radius` from the origin to the middle of
+/// its tube, which is `minor_radius` thick.
+pub fn torus(point: Point3, major_radius: f32, minor_radius: f32) -> f32 {
+    let around = (point.x() * point.x() + point.z() * point.z()).sqrt() - major_radius;
+    (around * around + point.y() * point.y()).sqrt() - minor_radius
+}
+
+/// Every point within `radius` of the line segment from `a` to `b`.
+pub fn capsule(point: Point3, a: Point3, b: Point3, radius: f32) -> f32 {
+    let pa = point - a;
+    let ba = b - a;
+    let h = (pa.dot(ba) / ba.dot(ba)).clamp(0.0, 1.0);
+    (pa - ba * h).length() - radius
+}
+
+/// Everything inside either shape.
+pub fn union(a: f32, b: f32) -> f32 {
+    a.min(b)
+}
+
+/// Like `union`, but blends the shapes together where they're within `k` of
+/// each other, like drops of liquid merging. Uses Inigo Quilez's polynomial
+/// smooth minimum.
+pub fn smooth_union(a: f32, b: f32, k: f32) -> f32 {
+    if k <= 0.0 {
+        return union(a, b);
+    }
+    let h = (0.5 + 0.5 * (b - a) / k).clamp(0.0, 1.0);
+    b * (1.0 - h) + a * h - k * h * (1.0 - h)
+}
+
+/// Everything inside both shapes.
+pub fn intersection(a: f32, b: f32) -> f32 {
+    a.max(b)
+}
+
+/// Shape `a`, with shape `b` cut out of it.
+pub fn subtraction(a: f32, b: f32) -> f32 {
+    a.max(-b)
+}
+
+/// Folds space so whatever is around the origin repeats every `period` units
+/// along each axis. Axes with a period of 0.0 don't repeat. Pass the result to
+/// a primitive in place of `point`.
+pub fn repeat(point: Point3, period: Vec3) -> Point3 {
+    let fold = |coordinate: f32, period: f32| {
+        if period == 0.0 {
+            coordinate
+        } else {
+            coordinate - period * (coordinate / period).round()
+        }
+    };
+    Point3::new(
+        fold(point.x(), period.x()),
+        fold(point.y(), period.y()),
+        fold(point.z(), period.z()),
+    )
+}
+
+/// The surface normal at `point`, estimated from how the distance changes
+/// either side of it along each axis.
+pub fn normal(sdf: impl Fn(Point3) -> f32, point: Point3) -> Vec3 {
+    const H: f32 = 0.0005;
+    let difference = |offset: Vec3| sdf(point + offset) - sdf(point - offset);
+    vec3(
+        difference(vec3(H, 0.0, 0.0)),
+        difference(vec3(0.0, H, 0.0)),
+        difference(vec3(0.0, 0.0, H)),
+    )
+    .normalize()
+}
+
+/// Renders signed distance fields by sphere tracing: stepping along each ray
+/// by the distance to the nearest surface, which can't overshoot it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SphereTracer {
+    /// Rays that haven't hit anything after this many steps miss.
+    pub max_steps: u32,
+    /// Rays that get this far without hitting anything miss.
+    pub max_distance: f32,
+    /// How close to a surface counts as hitting it.
+    pub epsilon: f32,
+}
+
+impl Default for SphereTracer {
+    fn default() -> Self {
+        SphereTracer {
+            max_steps: 256,
+            max_distance: 100.0,
+            epsilon: 0.0001,
+        }
+    }
+}
+
+impl SphereTracer {
+    pub fn new() -> SphereTracer {
+        SphereTracer::default()
+    }
+
+    pub fn with_max_steps(mut self, max_steps: u32) -> SphereTracer {
+        self.max_steps = max_steps;
+        self
+    }
+
+    pub fn with_max_distance(mut self, max_distance: f32) -> SphereTracer {
+        self.max_distance = max_distance;
+        self
+    }
+
+    /// How far along `ray` the first surface is, measured in units of
+    /// distance rather than of the ray's direction.
+    pub fn march(&self, sdf: impl Fn(Point3) -> f32, ray: Ray) -> Option<f32> {
+        self.march_up_to(sdf, ray, self.max_distance)
+    }
+
+    fn march_up_to(&self, sdf: impl Fn(Point3) -> f32, ray: Ray, max_distance: f32) -> Option<f32> {
+        let direction = ray.direction.normalize();
+        let mut t = 0.0;
+        for _ in 0..self.max_steps {
+            let distance = sdf(ray.origin + direction * t);
+            if distance.abs() < self.epsilon * (1.0 + t) {
+                return Some(t);
+            }
+            t += distance.abs();
+            if t > max_distance {
+                return None;
+            }
+        }
+        None
+    }
+
+    /// The color seen along `ray`: the surface of `sdf`, shaded with
+    /// `material` under every light with hard shadows, or the background.
+    pub fn color_at(
+        &self,
+        sdf: impl Fn(Point3) -> f32,
+        material: &Material,
+        lights: &[Box<dyn Light>],
+        background: &Background,
+        ray: Ray,
+    ) -> Rgb {
+        let Some(t) = self.march(&sdf, ray) else {
+            return background.color(ray.direction.normalize());
+        };
+        let eyev = -ray.direction.normalize();
+        let point = ray.origin - eyev * t;
+        let normalv = normal(&sdf, point);
+        // Step far enough off the surface that shadow rays don't start
+        // inside the hit threshold.
+        let over_point = point + normalv * (self.epsilon * (1.0 + t) * 10.0);
+
+        let mut total = material.emission;
+        for light in lights {
+            let light_intensity = self.intensity_at(&sdf, light.as_ref(), over_point);
+            total = total
+                + lighting(
+                    material,
+                    light.as_ref(),
+                    over_point,
+                    eyev,
+                    normalv,
+                    light_intensity,
+                );
+        }
+        total
+    }
+
+    /// Like `Light::intensity_at`, but marching shadow rays through `sdf`.
+    fn intensity_at(&self, sdf: impl Fn(Point3) -> f32, light: &dyn Light, point: Point3) -> f32 {
+        let falloff = light.falloff(point);
+        if falloff <= 0.0 {
+            return 0.0;
+        }
+        let samples = light.samples(point);
+        if samples.is_empty() {
+            return 0.0;
+        }
+        let num_visible = samples
+            .iter()
+            .filter(|sample| {
+                let shadow_ray = Ray::new(point, sample.direction);
+                let max_distance = sample.distance.min(self.max_distance);
+                self.march_up_to(&sdf, shadow_ray, max_distance).is_none()
+            })
+            .count();
+        falloff * num_visible as f32 / samples.len() as f32
+    }
+}
+
+/// Everything besides the field itself that `SphereTracer::color_at` shades
+/// it with.
+pub struct Stage {
+    pub material: Material,
+    pub lights: Vec<Box<dyn Light>>,
+    pub background: Background,
+}
+
+impl Stage {
+    /// A warm matte material lit from the upper left, against a pale sky.
+    pub fn studio() -> Stage {
+        Stage {
+            material: Material {
+                color: Rgb::new(1.0, 0.8, 0.6),
+                ..Material::default()
+            },
+            lights: vec![Box::new(PointLight::new(
+                pt3(-10.0, 10.0, -10.0),
+                Rgb::new(1.0, 1.0, 1.0),
+            ))],
+            background: Background::VerticalGradient {
+                bottom: Rgb::new(1.0, 1.0, 1.0),
+                top: Rgb::new(0.4, 0.6, 1.0),
+            },
+        }
+    }
+
+    /// Shades `sdf` on this stage. See `SphereTracer::color_at`.
+    pub fn color_at(&self, tracer: &SphereTracer, sdf: impl Fn(Point3) -> f32, ray: Ray) -> Rgb {
+        tracer.color_at(sdf, &self.material, &self.lights, &self.background, ray)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{pt3, Camera, PointLight, SpotLight, Transform};
+    use super::*;
+    use crate::extensions::F32Extension;
+    use crate::library::image_comparison::assert_scene_matches_golden_image;
+    use camino::Utf8Path;
+    use std::f32::consts::{PI, SQRT_2};
+
+    #[test]
+    fn distances_to_a_sphere() {
+        assert_eq!(sphere(pt3(0.0, 0.0, 0.0), 1.0), -1.0);
+        assert_eq!(sphere(pt3(0.0, 1.0, 0.0), 1.0), 0.0);
+        assert_eq!(sphere(pt3(3.0, 0.0, 0.0), 1.0), 2.0);
+    }
+
+    #[test]
+    fn distances_to_a_cuboid() {
+        let half_size = vec3(1.0, 1.0, 1.0);
+        assert_eq!(cuboid(pt3(0.0, 0.0, 0.0), half_size), -1.0);
+        assert_eq!(cuboid(pt3(2.0, 0.0, 0.0), half_size), 1.0);
+        assert!(cuboid(pt3(2.0, 2.0, 0.0), half_size).almost_eq(SQRT_2));
+        assert_eq!(cuboid(pt3(0.5, 0.0, 0.0), half_size), -0.5);
+    }
+
+    #[test]
+    fn distances_to_a_torus() {
+        assert_eq!(torus(pt3(2.0, 0.0, 0.0), 2.0, 0.5), -0.5);
+        assert_eq!(torus(pt3(0.0, 0.0, 0.0), 2.0, 0.5), 1.5);
+        assert_eq!(torus(pt3(0.0, 1.5, -2.0), 2.0, 0.5), 1.0);
+    }
+
+    #[test]
+    fn distances_to_a_capsule() {
+        let a = pt3(0.0, -1.0, 0.0);
+        let b = pt3(0.0, 1.0, 0.0);
+        assert_eq!(capsule(pt3(2.0, 0.0, 0.0), a, b, 0.5), 1.5);
+        assert_eq!(capsule(pt3(0.0, 3.0, 0.0), a, b, 0.5), 1.5);
+        assert_eq!(capsule(pt3(0.0, 0.0, 0.0), a, b, 0.5), -0.5);
+    }
+
+    #[test]
+    fn combining_distances() {
+        assert_eq!(union(1.0, 2.0), 1.0);
+        assert_eq!(intersection(1.0, 2.0), 2.0);
+        // Inside the cut-out shape is outside the result.
+        assert_eq!(subtraction(-1.0, -0.5), 0.5);
+        assert_eq!(subtraction(-1.0, 2.0), -1.0);
+    }
+
+    #[test]
+    fn smooth_union_only_blends_shapes_that_are_close() {
+        assert_eq!(smooth_union(1.0, 3.0, 0.5), 1.0);
+        assert!(smooth_union(1.0, 1.2, 0.5) < 1.0);
+        assert_eq!(smooth_union(1.0, 1.2, 0.0), 1.0);
+    }
+
+    #[test]
+    fn repeating_space() {
+        let period = vec3(4.0, 0.0, 4.0);
+        let point = pt3(0.5, 7.0, -1.0);
+        let repeated = repeat(point, period);
+        assert_eq!(repeated, pt3(0.5, 7.0, -1.0));
+        assert_eq!(repeat(pt3(8.5, 7.0, 3.0), period), repeated);
+    }
+
+    #[test]
+    fn marching_a_ray_to_a_sphere() {
+        let tracer = SphereTracer::new();
+        let unit_sphere = |p| sphere(p, 1.0);
+        let hit = Ray::new(pt3(0.0, 0.0, -5.0), vec3(0.0, 0.0, 2.0));
+        assert!(tracer.march(unit_sphere, hit).unwrap().almost_eq(4.0));
+        let miss = Ray::new(pt3(0.0, 2.0, -5.0), vec3(0.0, 0.0, 1.0));
+        assert_eq!(tracer.march(unit_sphere, miss), None);
+    }
+
+    #[test]
+    fn normals_by_central_differences() {
+        let unit_sphere = |p| sphere(p, 1.0);
+        let a = 3.0_f32.sqrt() / 3.0;
+        let sphere_normal = normal(unit_sphere, pt3(a, a, a));
+        assert!(
+            (sphere_normal - vec3(a, a, a)).length() < 0.001,
+            "{sphere_normal:?}"
+        );
+        let unit_cube = |p| cuboid(p, vec3(1.0, 1.0, 1.0));
+        let cube_normal = normal(unit_cube, pt3(0.2, 1.0, -0.4));
+        assert!(
+            (cube_normal - vec3(0.0, 1.0, 0.0)).length() < 0.001,
+            "{cube_normal:?}"
+        );
+    }
+
+    #[test]
+    fn surfaces_cast_shadows() {
+        let scene = |p: Point3| {
+            let floor = p.y() + 1.0;
+            union(floor, sphere(p, 0.5))
+        };
+        let lights: Vec<Box<dyn Light>> = vec![Box::new(PointLight::new(
+            pt3(0.0, 10.0, 0.0),
+            Rgb::new(1.0, 1.0, 1.0),
+        ))];
+        let material = Material::default();
+        let tracer = SphereTracer::new();
+        let shaded = |x: f32| {
+            let ray = Ray::new(pt3(x, 0.0, -5.0), vec3(0.0, -1.0, 5.0));
+            tracer.color_at(scene, &material, &lights, &Background::None, ray)
+        };
+        // Directly under the sphere is only lit by ambient light.
+        assert!(shaded(0.0).red().almost_eq(0.1));
+        assert!(shaded(3.0).red() > 0.9);
+    }
+
+    #[test]
+    fn spotlights_only_light_what_theyre_aimed_at() {
+        let floor = |p: Point3| p.y() + 1.0;
+        let material = Material {
+            ambient: 0.0,
+            specular: 0.0,
+            ..Material::default()
+        };
+        let tracer = SphereTracer::new();
+        let shaded = |direction: Vec3| {
+            let lights: Vec<Box<dyn Light>> = vec![Box::new(SpotLight::new(
+                pt3(0.0, 5.0, 0.0),
+                direction,
+                PI / 8.0,
+                PI / 4.0,
+                Rgb::new(1.0, 1.0, 1.0),
+            ))];
+            let ray = Ray::new(pt3(0.0, 0.0, -5.0), vec3(0.0, -1.0, 5.0));
+            tracer.color_at(floor, &material, &lights, &Background::None, ray)
+        };
+        assert!(shaded(vec3(0.0, -1.0, 0.0)).red().almost_eq(0.9));
+        assert_eq!(shaded(vec3(0.0, 1.0, 0.0)), Rgb::black());
+    }
+
+    #[test]
+    fn sdf_scene_matches_golden_image() {
+        let scene = |p: Point3| {
+            let floor = p.y() + 1.0;
+            let blob = smooth_union(
+                sphere(p - vec3(-0.4, 0.0, 0.0), 0.6),
+                sphere(p - vec3(0.4, 0.1, 0.0), 0.5),
+                0.3,
+            );
+            let ring = torus(p - vec3(0.0, -0.8, 0.0), 1.4, 0.15);
+            let hollow_box = subtraction(
+                cuboid(p - vec3(1.8, -0.5, 1.5), vec3(0.5, 0.5, 0.5)),
+                sphere(p - vec3(1.8, -0.5, 1.5), 0.65),
+            );
+            let posts = capsule(
+                repeat(p, vec3(3.0, 0.0, 0.0)) - vec3(0.0, 0.0, 3.0),
+                pt3(0.0, -1.0, 0.0),
+                pt3(0.0, 1.0, 0.0),
+                0.2,
+            );
+            union(union(union(floor, blob), union(ring, hollow_box)), posts)
+        };
+        let stage = Stage::studio();
+        let tracer = SphereTracer::new();
+
+        assert_scene_matches_golden_image(
+            |ray| stage.color_at(&tracer, scene, ray),
+            pt3(0.0, 1.5, -5.0),
+            pt3(0.0, -0.25, 0.0),
+            Utf8Path::new("src/tests/sdf_scene_expected_output.png"),
+        );
+    }
+}
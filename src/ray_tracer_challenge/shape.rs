@@ -0,0 +1,452 @@
+use std::borrow::Cow;
+
+use glam::Vec4Swizzles;
+
+use super::{pt3, vec3, Intersection, Material, Motion, Point3, Ray, Transform, Vec3};
+use crate::ray_tracer_challenge::EPSILON;
+
+/// The untransformed form of a shape, in its own object space.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Geometry {
+    /// A sphere of radius 1.0 centered on the origin.
+    Sphere,
+    /// The infinite xz plane.
+    Plane,
+    /// An axis-aligned cube from -1.0 to 1.0 on every axis.
+    Cube,
+}
+
+#[derive(Debug, Clone)]
+pub struct Shape {
+    pub geometry: Geometry,
+    pub material: Material,
+    transform: Transform,
+    inverse_transform: Transform,
+    /// Overrides `transform` with one that changes over time, for motion blur.
+    pub motion: Option<Motion>,
+}
+
+impl Shape {
+    pub fn new(geometry: Geometry) -> Shape {
+        Shape {
+            geometry,
+            material: Material::default(),
+            transform: Transform::identity(),
+            inverse_transform: Transform::identity(),
+            motion: None,
+        }
+    }
+
+    pub fn sphere() -> Shape {
+        Shape::new(Geometry::Sphere)
+    }
+
+    pub fn plane() -> Shape {
+        Shape::new(Geometry::Plane)
+    }
+
+    pub fn cube() -> Shape {
+        Shape::new(Geometry::Cube)
+    }
+
+    pub fn with_transform(mut self, transform: Transform) -> Shape {
+        self.set_transform(transform);
+        self
+    }
+
+    pub fn with_material(mut self, material: Material) -> Shape {
+        self.material = material;
+        self
+    }
+
+    pub fn with_motion(mut self, motion: Motion) -> Shape {
+        self.motion = Some(motion);
+        self
+    }
+
+    pub fn transform(&self) -> Transform {
+        self.transform
+    }
+
+    pub fn set_transform(&mut self, transform: Transform) {
+        self.transform = transform;
+        self.inverse_transform = transform.inverse();
+    }
+
+    /// The transform at `time`, which is only different from `transform` if
+    /// the shape is moving.
+    pub fn transform_at(&self, time: f32) -> Transform {
+        match &self.motion {
+            Some(motion) => motion.at(time),
+            None => self.transform,
+        }
+    }
+
+    fn inverse_transform_at(&self, time: f32) -> Transform {
+        match &self.motion {
+            Some(motion) => motion.at(time).inverse(),
+            None => self.inverse_transform,
+        }
+    }
+
+    /// Every point where the ray crosses the shape's surface, in no particular
+    /// order. Moving shapes are intersected where they were at the ray's time.
+    pub fn intersect(&self, ray: Ray) -> Vec<Intersection<'_>> {
+        let local_ray = ray.transform(self.inverse_transform_at(ray.time));
+        self.local_intersect(local_ray)
+            .into_iter()
+            .map(|t| Intersection::new(t, self))
+            .collect()
+    }
+
+    /// The surface normal at `world_point`, which is assumed to be on the
+    /// surface, tilted by the material's bump map if it has one.
+    pub fn normal_at(&self, world_point: Point3) -> Vec3 {
+        self.normal_at_time(world_point, 0.0)
+    }
+
+    /// Like `normal_at`, but for wherever the shape was at `time`.
+    pub fn normal_at_time(&self, world_point: Point3, time: f32) -> Vec3 {
+        self.normals_at_time(world_point, time).1
+    }
+
+    /// The surface normal at `world_point` before and after the bump map
+    /// tilts it, as `(geometric, bumped)`. They're the same without a bump map.
+    pub fn normals_at_time(&self, world_point: Point3, time: f32) -> (Vec3, Vec3) {
+        let inverse_transform = self.inverse_transform_at(time);
+        let local_point = inverse_transform * world_point;
+        let local_normal = self.local_normal_at(local_point);
+        let bumped_normal = match &self.material.bump {
+            Some(bump) => bump.perturb(local_point, local_normal),
+            None => local_normal,
+        };
+        let to_world = |normal: Vec3| {
+            let world_normal = inverse_transform.transpose().matrix * normal.vec;
+            // The transpose can leave w nonzero if the transform includes a
+            // translation.
+            let xyz = world_normal.xyz();
+            vec3(xyz.x, xyz.y, xyz.z).normalize()
+        };
+        (to_world(local_normal), to_world(bumped_normal))
+    }
+
+    /// Converts a point in world space to the shape's object space, as it was
+    /// at `time`.
+    pub fn world_to_object(&self, world_point: Point3, time: f32) -> Point3 {
+        self.inverse_transform_at(time) * world_point
+    }
+
+    /// The material as it is at `world_point`, with its pattern, if it has
+    /// one, filled in as the color.
+    pub fn material_at(&self, world_point: Point3, time: f32) -> Cow<'_, Material> {
+        match &self.material.pattern {
+            Some(pattern) => Cow::Owned(Material {
+                color: pattern.color_at_shape(self, world_point, time),
+                ..self.material.clone()
+            }),
+            None => Cow::Borrowed(&self.material),
+        }
+    }
+
+    fn local_intersect(&self, ray: Ray) -> Vec<f32> {
+        match self.geometry {
+            Geometry::Sphere => {
+                let sphere_to_ray = ray.origin - pt3(0.0, 0.0, 0.0);
+                let a = ray.direction.dot(ray.direction);
+                let b = 2.0 * ray.direction.dot(sphere_to_ray);
+                let c = sphere_to_ray.dot(sphere_to_ray) - 1.0;
+                let discriminant = b * b - 4.0 * a * c;
+                if discriminant < 0.0 {
+                    return vec![];
+                }
+                let root = discriminant.sqrt();
+                vec![(-b - root) / (2.0 * a), (-b + root) / (2.0 * a)]
+            }
+            Geometry::Plane => {
+                if ray.direction.y().abs() < EPSILON {
+                    return vec![];
+                }
+                vec![-ray.origin.y() / ray.direction.y()]
+            }
+            Geometry::Cube => {
+                let (x_min, x_max) = check_axis(ray.origin.x(), ray.direction.x());
+                let (y_min, y_max) = check_axis(ray.origin.y(), ray.direction.y());
+                let (z_min, z_max) = check_axis(ray.origin.z(), ray.direction.z());
+                let t_min = x_min.max(y_min).max(z_min);
+                let t_max = x_max.min(y_max).min(z_max);
+                if t_min > t_max {
+                    return vec![];
+                }
+                vec![t_min, t_max]
+            }
+        }
+    }
+
+    fn local_normal_at(&self, local_point: Point3) -> Vec3 {
+        match self.geometry {
+            Geometry::Sphere => local_point - pt3(0.0, 0.0, 0.0),
+            Geometry::Plane => vec3(0.0, 1.0, 0.0),
+            Geometry::Cube => {
+                let (x, y, z) = (local_point.x(), local_point.y(), local_point.z());
+                let largest = x.abs().max(y.abs()).max(z.abs());
+                if largest == x.abs() {
+                    vec3(x, 0.0, 0.0)
+                } else if largest == y.abs() {
+                    vec3(0.0, y, 0.0)
+                } else {
+                    vec3(0.0, 0.0, z)
+                }
+            }
+        }
+    }
+}
+
+/// Where a ray enters and leaves the slab between -1.0 and 1.0 on one axis.
+fn check_axis(origin: f32, direction: f32) -> (f32, f32) {
+    let t_min_numerator = -1.0 - origin;
+    let t_max_numerator = 1.0 - origin;
+    let (t_min, t_max) = if direction.abs() >= EPSILON {
+        (t_min_numerator / direction, t_max_numerator / direction)
+    } else {
+        (
+            t_min_numerator * f32::INFINITY,
+            t_max_numerator * f32::INFINITY,
+        )
+    };
+    if t_min > t_max {
+        (t_max, t_min)
+    } else {
+        (t_min, t_max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::extensions::F32Extension;
+    use std::f32::consts::{FRAC_1_SQRT_2, PI};
+
+    fn ts(intersections: &[Intersection]) -> Vec<f32> {
+        intersections
+            .iter()
+            .map(|intersection| intersection.t)
+            .collect()
+    }
+
+    #[test]
+    fn a_ray_intersects_a_sphere_at_two_points() {
+        let ray = Ray::new(pt3(0.0, 0.0, -5.0), vec3(0.0, 0.0, 1.0));
+        let sphere = Shape::sphere();
+        assert_eq!(ts(&sphere.intersect(ray)), vec![4.0, 6.0]);
+    }
+
+    #[test]
+    fn a_ray_intersects_a_sphere_at_a_tangent() {
+        let ray = Ray::new(pt3(0.0, 1.0, -5.0), vec3(0.0, 0.0, 1.0));
+        let sphere = Shape::sphere();
+        assert_eq!(ts(&sphere.intersect(ray)), vec![5.0, 5.0]);
+    }
+
+    #[test]
+    fn a_ray_misses_a_sphere() {
+        let ray = Ray::new(pt3(0.0, 2.0, -5.0), vec3(0.0, 0.0, 1.0));
+        assert!(Shape::sphere().intersect(ray).is_empty());
+    }
+
+    #[test]
+    fn a_ray_originates_inside_a_sphere() {
+        let ray = Ray::new(pt3(0.0, 0.0, 0.0), vec3(0.0, 0.0, 1.0));
+        assert_eq!(ts(&Shape::sphere().intersect(ray)), vec![-1.0, 1.0]);
+    }
+
+    #[test]
+    fn a_sphere_is_behind_a_ray() {
+        let ray = Ray::new(pt3(0.0, 0.0, 5.0), vec3(0.0, 0.0, 1.0));
+        assert_eq!(ts(&Shape::sphere().intersect(ray)), vec![-6.0, -4.0]);
+    }
+
+    #[test]
+    fn intersecting_a_scaled_sphere_with_a_ray() {
+        let ray = Ray::new(pt3(0.0, 0.0, -5.0), vec3(0.0, 0.0, 1.0));
+        let sphere = Shape::sphere().with_transform(Transform::scaling(2.0, 2.0, 2.0));
+        assert_eq!(ts(&sphere.intersect(ray)), vec![3.0, 7.0]);
+    }
+
+    #[test]
+    fn intersecting_a_translated_sphere_with_a_ray() {
+        let ray = Ray::new(pt3(0.0, 0.0, -5.0), vec3(0.0, 0.0, 1.0));
+        let sphere = Shape::sphere().with_transform(Transform::translation(5.0, 0.0, 0.0));
+        assert!(sphere.intersect(ray).is_empty());
+    }
+
+    #[test]
+    fn the_normal_on_a_sphere_is_a_normalized_vector() {
+        let sphere = Shape::sphere();
+        let a = 3.0_f32.sqrt() / 3.0;
+        let normal = sphere.normal_at(pt3(a, a, a));
+        assert_eq!(normal, vec3(a, a, a));
+        assert!(normal.length().almost_eq(1.0));
+    }
+
+    #[test]
+    fn computing_the_normal_on_a_translated_sphere() {
+        let sphere = Shape::sphere().with_transform(Transform::translation(0.0, 1.0, 0.0));
+        let normal = sphere.normal_at(pt3(0.0, 1.0 + FRAC_1_SQRT_2, -FRAC_1_SQRT_2));
+        assert_eq!(normal, vec3(0.0, FRAC_1_SQRT_2, -FRAC_1_SQRT_2));
+    }
+
+    #[test]
+    fn computing_the_normal_on_a_transformed_sphere() {
+        let transform = Transform::rotation_z(PI / 5.0).then(Transform::scaling(1.0, 0.5, 1.0));
+        let sphere = Shape::sphere().with_transform(transform);
+        let normal = sphere.normal_at(pt3(0.0, FRAC_1_SQRT_2, -FRAC_1_SQRT_2));
+        assert!((normal - vec3(0.0, 0.97014, -0.24254)).length() < 0.0001);
+    }
+
+    #[test]
+    fn the_normal_of_a_plane_is_constant_everywhere() {
+        let plane = Shape::plane();
+        assert_eq!(plane.normal_at(pt3(0.0, 0.0, 0.0)), vec3(0.0, 1.0, 0.0));
+        assert_eq!(plane.normal_at(pt3(10.0, 0.0, -10.0)), vec3(0.0, 1.0, 0.0));
+        assert_eq!(plane.normal_at(pt3(-5.0, 0.0, 150.0)), vec3(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn intersecting_a_plane_with_parallel_and_coplanar_rays() {
+        let plane = Shape::plane();
+        let parallel = Ray::new(pt3(0.0, 10.0, 0.0), vec3(0.0, 0.0, 1.0));
+        assert!(plane.intersect(parallel).is_empty());
+        let coplanar = Ray::new(pt3(0.0, 0.0, 0.0), vec3(0.0, 0.0, 1.0));
+        assert!(plane.intersect(coplanar).is_empty());
+    }
+
+    #[test]
+    fn a_ray_intersecting_a_plane_from_above_and_below() {
+        let plane = Shape::plane();
+        let from_above = Ray::new(pt3(0.0, 1.0, 0.0), vec3(0.0, -1.0, 0.0));
+        assert_eq!(ts(&plane.intersect(from_above)), vec![1.0]);
+        let from_below = Ray::new(pt3(0.0, -1.0, 0.0), vec3(0.0, 1.0, 0.0));
+        assert_eq!(ts(&plane.intersect(from_below)), vec![1.0]);
+    }
+
+    #[test]
+    fn a_ray_intersects_a_cube() {
+        let cases = [
+            (pt3(5.0, 0.5, 0.0), vec3(-1.0, 0.0, 0.0), 4.0, 6.0),
+            (pt3(-5.0, 0.5, 0.0), vec3(1.0, 0.0, 0.0), 4.0, 6.0),
+            (pt3(0.5, 5.0, 0.0), vec3(0.0, -1.0, 0.0), 4.0, 6.0),
+            (pt3(0.5, -5.0, 0.0), vec3(0.0, 1.0, 0.0), 4.0, 6.0),
+            (pt3(0.5, 0.0, 5.0), vec3(0.0, 0.0, -1.0), 4.0, 6.0),
+            (pt3(0.5, 0.0, -5.0), vec3(0.0, 0.0, 1.0), 4.0, 6.0),
+            (pt3(0.0, 0.5, 0.0), vec3(0.0, 0.0, 1.0), -1.0, 1.0),
+        ];
+        let cube = Shape::cube();
+        for (origin, direction, t1, t2) in cases {
+            let ray = Ray::new(origin, direction);
+            assert_eq!(ts(&cube.intersect(ray)), vec![t1, t2], "{origin:?}");
+        }
+    }
+
+    #[test]
+    fn a_ray_misses_a_cube() {
+        let cases = [
+            (pt3(-2.0, 0.0, 0.0), vec3(0.2673, 0.5345, 0.8018)),
+            (pt3(0.0, -2.0, 0.0), vec3(0.8018, 0.2673, 0.5345)),
+            (pt3(0.0, 0.0, -2.0), vec3(0.5345, 0.8018, 0.2673)),
+            (pt3(2.0, 0.0, 2.0), vec3(0.0, 0.0, -1.0)),
+            (pt3(0.0, 2.0, 2.0), vec3(0.0, -1.0, 0.0)),
+            (pt3(2.0, 2.0, 0.0), vec3(-1.0, 0.0, 0.0)),
+        ];
+        let cube = Shape::cube();
+        for (origin, direction) in cases {
+            let ray = Ray::new(origin, direction);
+            assert!(cube.intersect(ray).is_empty(), "{origin:?}");
+        }
+    }
+
+    #[test]
+    fn the_normal_on_the_surface_of_a_cube() {
+        let cases = [
+            (pt3(1.0, 0.5, -0.8), vec3(1.0, 0.0, 0.0)),
+            (pt3(-1.0, -0.2, 0.9), vec3(-1.0, 0.0, 0.0)),
+            (pt3(-0.4, 1.0, -0.1), vec3(0.0, 1.0, 0.0)),
+            (pt3(0.3, -1.0, -0.7), vec3(0.0, -1.0, 0.0)),
+            (pt3(-0.6, 0.3, 1.0), vec3(0.0, 0.0, 1.0)),
+            (pt3(0.4, 0.4, -1.0), vec3(0.0, 0.0, -1.0)),
+            (pt3(1.0, 1.0, 1.0), vec3(1.0, 0.0, 0.0)),
+            (pt3(-1.0, -1.0, -1.0), vec3(-1.0, 0.0, 0.0)),
+        ];
+        let cube = Shape::cube();
+        for (point, expected) in cases {
+            assert_eq!(cube.normal_at(point), expected, "{point:?}");
+        }
+    }
+
+    #[test]
+    fn a_cube_map_pattern_colors_each_face_of_a_cube() {
+        use super::super::{CubeMap, Pattern, Rgb, Texture, UvPattern};
+
+        let red = Rgb::new(1.0, 0.0, 0.0);
+        let blue = Rgb::new(0.0, 0.0, 1.0);
+        let faces =
+            [red, blue, red, red, red, red].map(|rgb| UvPattern::Image(Texture::solid(rgb)));
+        let cube = Shape::cube().with_material(Material {
+            pattern: Some(Pattern::cube_map(CubeMap::new(faces))),
+            ..Material::default()
+        });
+        assert_eq!(cube.material_at(pt3(0.2, 0.3, 1.0), 0.0).color, blue);
+        assert_eq!(cube.material_at(pt3(-1.0, 0.3, 0.2), 0.0).color, red);
+        assert_eq!(
+            Shape::cube().material_at(pt3(0.0, 0.0, 1.0), 0.0).color,
+            Material::default().color
+        );
+    }
+
+    #[test]
+    fn a_bump_map_tilts_the_normal_before_it_is_transformed() {
+        use super::super::{BumpMap, Rgb, Texture, UvMapping};
+
+        let tilted = Texture::solid(Rgb::new(1.0, 0.5, 0.5));
+        let plane = Shape::plane()
+            .with_transform(Transform::rotation_z(PI / 2.0))
+            .with_material(Material {
+                bump: Some(BumpMap::normal_map(tilted, UvMapping::Planar)),
+                ..Material::default()
+            });
+        // In object space the normal points along +x, which the rotation
+        // turns to +y.
+        assert_eq!(plane.normal_at(pt3(0.0, 0.3, 0.2)), vec3(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn a_moving_shape_is_intersected_where_it_was_at_the_rays_time() {
+        let motion = Motion::linear(
+            Transform::translation(0.0, 0.0, 0.0),
+            Transform::translation(4.0, 0.0, 0.0),
+        );
+        let sphere = Shape::sphere().with_motion(motion);
+        let ray = Ray::new(pt3(4.0, 0.0, -5.0), vec3(0.0, 0.0, 1.0));
+        assert!(sphere.intersect(ray).is_empty());
+        let ts: Vec<f32> = sphere
+            .intersect(ray.with_time(1.0))
+            .iter()
+            .map(|i| i.t)
+            .collect();
+        assert_eq!(ts, vec![4.0, 6.0]);
+    }
+
+    #[test]
+    fn the_normal_on_a_moving_shape() {
+        let motion = Motion::linear(
+            Transform::translation(0.0, 0.0, 0.0),
+            Transform::translation(0.0, 2.0, 0.0),
+        );
+        let sphere = Shape::sphere().with_motion(motion);
+        let normal = sphere.normal_at_time(pt3(0.0, 2.0, -1.0), 1.0);
+        assert_eq!(normal, vec3(0.0, 0.0, -1.0));
+        assert_eq!(
+            sphere.transform_at(0.5),
+            Transform::translation(0.0, 1.0, 0.0)
+        );
+    }
+}
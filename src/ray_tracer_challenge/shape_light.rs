@@ -0,0 +1,305 @@
+use std::f32::consts::{PI, TAU};
+
+use rand::Rng;
+
+use super::{
+    pt3, rng_for_point, vec3, Geometry, Light, LightSample, Point3, Rgb, Shape, Transform, Vec3,
+    EPSILON,
+};
+
+/// Lights the world with an emissive shape's glow by sampling points on its
+/// surface, so glowing objects light their surroundings and cast soft shadows
+/// without a separate light. `World` creates one for every emissive shape it
+/// can sample.
+///
+/// Spheres and cubes can be sampled. Planes are infinite, so an emissive
+/// plane glows but doesn't light anything else. Moving shapes are sampled
+/// where they are at the time the light is created for.
+#[derive(Debug, Clone)]
+pub struct ShapeLight<'a> {
+    shape: &'a Shape,
+    transform: Transform,
+    inverse_transform: Transform,
+    normal_transform: Transform,
+    num_samples: u32,
+}
+
+impl<'a> ShapeLight<'a> {
+    pub const DEFAULT_NUM_SAMPLES: u32 = 16;
+
+    /// Samples the shape where it is at `time`. Returns `None` unless
+    /// `ShapeLight::can_sample(shape)`.
+    pub fn new(shape: &'a Shape, time: f32, num_samples: u32) -> Option<ShapeLight<'a>> {
+        if !ShapeLight::can_sample(shape) {
+            return None;
+        }
+        let transform = shape.transform_at(time);
+        let inverse_transform = transform.inverse();
+        Some(ShapeLight {
+            shape,
+            transform,
+            inverse_transform,
+            normal_transform: inverse_transform.transpose(),
+            num_samples: num_samples.max(1),
+        })
+    }
+
+    /// Whether `shape` emits light and has a surface that can be sampled.
+    pub fn can_sample(shape: &Shape) -> bool {
+        shape.material.emission != Rgb::black()
+            && matches!(shape.geometry, Geometry::Sphere | Geometry::Cube)
+    }
+
+    /// The area of the untransformed shape's surface.
+    fn local_area(&self) -> f32 {
+        match self.shape.geometry {
+            Geometry::Cube => 24.0,
+            _ => 2.0 * TAU,
+        }
+    }
+
+    fn local_contains(&self, local_point: Point3) -> bool {
+        let (x, y, z) = (local_point.x(), local_point.y(), local_point.z());
+        match self.shape.geometry {
+            Geometry::Cube => x.abs() < 1.0 && y.abs() < 1.0 && z.abs() < 1.0,
+            _ => x * x + y * y + z * z < 1.0,
+        }
+    }
+
+    /// A uniformly distributed point on the untransformed shape's surface, and
+    /// the normal there.
+    fn local_sample(&self, rng: &mut impl Rng) -> (Point3, Vec3) {
+        match self.shape.geometry {
+            Geometry::Cube => {
+                // Every face has the same area, so pick one, then a point on it.
+                let face = rng.gen_range(0..6);
+                let sign = if face % 2 == 0 { 1.0 } else { -1.0 };
+                let u = 2.0 * rng.gen::<f32>() - 1.0;
+                let v = 2.0 * rng.gen::<f32>() - 1.0;
+                match face / 2 {
+                    0 => (pt3(sign, u, v), vec3(sign, 0.0, 0.0)),
+                    1 => (pt3(u, sign, v), vec3(0.0, sign, 0.0)),
+                    _ => (pt3(u, v, sign), vec3(0.0, 0.0, sign)),
+                }
+            }
+            _ => {
+                let z = 1.0 - 2.0 * rng.gen::<f32>();
+                let radius = (1.0 - z * z).max(0.0).sqrt();
+                let angle = TAU * rng.gen::<f32>();
+                let (x, y) = (radius * angle.cos(), radius * angle.sin());
+                (pt3(x, y, z), vec3(x, y, z))
+            }
+        }
+    }
+}
+
+impl Light for ShapeLight<'_> {
+    fn intensity(&self) -> Rgb {
+        self.shape.material.emission
+    }
+
+    /// Glowing shapes are often much brighter than other lights, and their
+    /// ambient light would show up even where they're hidden.
+    fn contributes_ambient(&self) -> bool {
+        false
+    }
+
+    /// Uniformly distributed points on the part of the shape visible from
+    /// `point`, which is all of it from the inside. Each is weighted by the
+    /// solid angle of the patch of surface it stands for, so small or distant
+    /// shapes give off less light.
+    fn samples(&self, point: Point3) -> Vec<LightSample> {
+        let mut rng = rng_for_point(point, 1);
+        let determinant = self.transform.matrix.determinant().abs();
+        let is_inside = self.local_contains(self.inverse_transform * point);
+        // The surface is split evenly between the samples, and the irradiance
+        // is divided by π to match other lights.
+        let area_per_sample = self.local_area() / PI;
+
+        let mut samples: Vec<LightSample> = (0..self.num_samples)
+            .filter_map(|_| {
+                let (local_point, local_normal) = self.local_sample(&mut rng);
+                let surface_point = self.transform * local_point;
+                let scaled_normal = self.normal_transform * local_normal;
+                let scaled_normal = vec3(scaled_normal.x(), scaled_normal.y(), scaled_normal.z());
+                // How much the transform stretches the surface around this point.
+                let area_scale = determinant * scaled_normal.length();
+
+                let v = surface_point - point;
+                let distance = v.length();
+                let direction = v.normalize();
+                let mut cos_light = direction.dot(scaled_normal.normalize());
+                if !is_inside {
+                    cos_light = -cos_light;
+                }
+                // From outside, points on the far side face away, and are
+                // hidden anyway.
+                if cos_light <= 0.0 {
+                    return None;
+                }
+                let weight = area_per_sample * cos_light * area_scale / (distance * distance);
+                Some(LightSample {
+                    direction,
+                    // Stop shadow rays just short of the surface, so the shape
+                    // doesn't shadow itself.
+                    distance: distance - EPSILON,
+                    weight,
+                })
+            })
+            .collect();
+
+        // Callers average over the samples that are returned, so make up for
+        // the ones on the far side.
+        let kept_fraction = samples.len() as f32 / self.num_samples as f32;
+        for sample in &mut samples {
+            sample.weight *= kept_fraction;
+        }
+        samples
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{Material, Motion, World};
+    use super::*;
+
+    fn glowing_sphere(transform: Transform) -> Shape {
+        glowing(Shape::sphere(), transform)
+    }
+
+    fn glowing(shape: Shape, transform: Transform) -> Shape {
+        shape.with_transform(transform).with_material(Material {
+            emission: Rgb::new(2.0, 2.0, 2.0),
+            ..Material::default()
+        })
+    }
+
+    /// The average of `weight * cos` over the samples, which is the
+    /// irradiance divided by π.
+    fn irradiance(light: &ShapeLight, point: Point3) -> f32 {
+        let samples = light.samples(point);
+        let up = vec3(0.0, 1.0, 0.0);
+        let total: f32 = samples
+            .iter()
+            .map(|sample| sample.weight * sample.direction.dot(up).max(0.0))
+            .sum();
+        total / samples.len() as f32
+    }
+
+    #[test]
+    fn only_emissive_spheres_and_cubes_can_be_sampled() {
+        assert!(ShapeLight::can_sample(&glowing_sphere(
+            Transform::identity()
+        )));
+        assert!(ShapeLight::can_sample(&glowing(
+            Shape::cube(),
+            Transform::identity()
+        )));
+        assert!(!ShapeLight::can_sample(&Shape::sphere()));
+        assert!(!ShapeLight::can_sample(&Shape::cube()));
+        let mut plane = Shape::plane();
+        plane.material.emission = Rgb::new(1.0, 1.0, 1.0);
+        assert!(ShapeLight::new(&plane, 0.0, 16).is_none());
+    }
+
+    #[test]
+    fn a_glowing_sphere_lights_the_point_below_it() {
+        // The irradiance from a sphere of radius r straight above a point,
+        // centered h away, is π L (r / h)², so the irradiance over π is r²/h².
+        for (radius, height) in [(1.0, 4.0), (2.0, 5.0)] {
+            let transform = Transform::scaling(radius, radius, radius)
+                .then(Transform::translation(0.0, height, 0.0));
+            let sphere = glowing_sphere(transform);
+            let light = ShapeLight::new(&sphere, 0.0, 4096).unwrap();
+            let expected = (radius / height).powi(2);
+            let actual = irradiance(&light, pt3(0.0, 0.0, 0.0));
+            assert!(
+                (actual - expected).abs() < expected * 0.05,
+                "{actual} {expected}"
+            );
+        }
+    }
+
+    #[test]
+    fn a_glowing_cube_lights_the_point_below_it() {
+        // The irradiance over π from a rectangle a by b straight above one
+        // corner, h away and facing down, is
+        // (A atan(B / √(1 + A²)) / √(1 + A²) + B atan(A / √(1 + B²)) / √(1 + B²)) / 2π,
+        // where A = a / h and B = b / h. The cube's bottom face is four of those.
+        let height: f32 = 3.0;
+        let side = 1.0 / height;
+        let root = (1.0 + side * side).sqrt();
+        let expected = 4.0 * 2.0 * side * (side / root).atan() / root / TAU;
+
+        let cube = glowing(
+            Shape::cube(),
+            Transform::translation(0.0, height + 1.0, 0.0),
+        );
+        let light = ShapeLight::new(&cube, 0.0, 4096).unwrap();
+        let actual = irradiance(&light, pt3(0.0, 0.0, 0.0));
+        assert!(
+            (actual - expected).abs() < expected * 0.05,
+            "{actual} {expected}"
+        );
+    }
+
+    #[test]
+    fn samples_only_cover_the_side_facing_the_point() {
+        let sphere = glowing_sphere(Transform::translation(0.0, 4.0, 0.0));
+        let light = ShapeLight::new(&sphere, 0.0, 64).unwrap();
+        let samples = light.samples(pt3(0.0, 0.0, 0.0));
+        assert!(!samples.is_empty() && samples.len() < 64);
+        for sample in samples {
+            assert!(sample.distance >= 3.0 - EPSILON && sample.distance < 4.0);
+        }
+    }
+
+    #[test]
+    fn the_shape_does_not_shadow_its_own_light() {
+        let sphere = glowing_sphere(Transform::translation(0.0, 4.0, 0.0));
+        let light = ShapeLight::new(&sphere, 0.0, 64).unwrap();
+        let world = World {
+            objects: vec![sphere.clone()],
+            lights: vec![],
+            ..World::default()
+        };
+        assert_eq!(light.intensity_at(pt3(0.0, 0.0, 0.0), 0.0, &world), 1.0);
+    }
+
+    #[test]
+    fn the_whole_shape_is_sampled_from_the_inside() {
+        for shape in [Shape::sphere(), Shape::cube()] {
+            let shape = glowing(shape, Transform::scaling(10.0, 10.0, 10.0));
+            let light = ShapeLight::new(&shape, 0.0, 64).unwrap();
+            let samples = light.samples(pt3(0.0, 0.0, 0.0));
+            assert_eq!(samples.len(), 64);
+            // Inside a closed shape, the irradiance over π is exactly the radiance.
+            let actual = irradiance(
+                &ShapeLight::new(&shape, 0.0, 16384).unwrap(),
+                pt3(0.0, 0.0, 0.0),
+            );
+            assert!(
+                (actual - 1.0).abs() < 0.05,
+                "{:?}: {actual}",
+                shape.geometry
+            );
+        }
+    }
+
+    #[test]
+    fn moving_shapes_are_sampled_where_they_are_at_the_time() {
+        let motion = Motion::linear(
+            Transform::translation(0.0, 4.0, 0.0),
+            Transform::translation(100.0, 4.0, 0.0),
+        );
+        let sphere = glowing_sphere(Transform::identity()).with_motion(motion);
+        for (time, center) in [(0.0, pt3(0.0, 4.0, 0.0)), (1.0, pt3(100.0, 4.0, 0.0))] {
+            let light = ShapeLight::new(&sphere, time, 64).unwrap();
+            for sample in light.samples(pt3(0.0, 0.0, 0.0)) {
+                let surface_point = pt3(0.0, 0.0, 0.0) + sample.direction * sample.distance;
+                let radius = (surface_point - center).length();
+                assert!((radius - 1.0).abs() < 0.001, "{time}: {radius}");
+            }
+        }
+    }
+}
@@ -0,0 +1,139 @@
+use super::{Attenuation, Light, LightSample, Point3, Rgb, Vec3};
+
+/// A point light that only shines in a cone, like a lamp fixture. Points
+/// within `inner_angle` of `direction` are fully lit, points beyond
+/// `outer_angle` are unlit, and the light fades smoothly in between.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpotLight {
+    pub position: Point3,
+    direction: Vec3,
+    /// In radians, measured from `direction`.
+    pub inner_angle: f32,
+    /// In radians, measured from `direction`.
+    pub outer_angle: f32,
+    pub intensity: Rgb,
+    pub attenuation: Attenuation,
+}
+
+impl SpotLight {
+    pub fn new(
+        position: Point3,
+        direction: Vec3,
+        inner_angle: f32,
+        outer_angle: f32,
+        intensity: Rgb,
+    ) -> SpotLight {
+        SpotLight {
+            position,
+            direction: direction.normalize(),
+            inner_angle,
+            outer_angle: outer_angle.max(inner_angle),
+            intensity,
+            attenuation: Attenuation::none(),
+        }
+    }
+
+    pub fn with_attenuation(mut self, attenuation: Attenuation) -> SpotLight {
+        self.attenuation = attenuation;
+        self
+    }
+
+    /// The normalized direction the cone points in.
+    pub fn direction(&self) -> Vec3 {
+        self.direction
+    }
+}
+
+impl Light for SpotLight {
+    fn intensity(&self) -> Rgb {
+        self.intensity
+    }
+
+    fn attenuation(&self) -> Attenuation {
+        self.attenuation
+    }
+
+    fn samples(&self, point: Point3) -> Vec<LightSample> {
+        vec![LightSample::toward(point, self.position)]
+    }
+
+    fn falloff(&self, point: Point3) -> f32 {
+        let to_point = point - self.position;
+        // A point at the light itself has no direction from it, so it can't
+        // be outside the cone.
+        if to_point.length() == 0.0 {
+            return 1.0;
+        }
+        let cos_angle = to_point.normalize().dot(self.direction);
+        let cos_inner = self.inner_angle.cos();
+        let cos_outer = self.outer_angle.cos();
+        if cos_angle >= cos_inner {
+            return 1.0;
+        }
+        if cos_angle <= cos_outer {
+            return 0.0;
+        }
+        let t = (cos_angle - cos_outer) / (cos_inner - cos_outer);
+        t * t * (3.0 - 2.0 * t)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{pt3, vec3, World};
+    use super::*;
+    use std::f32::consts::PI;
+
+    fn downlight() -> SpotLight {
+        SpotLight::new(
+            pt3(0.0, 1.0, 0.0),
+            vec3(0.0, -2.0, 0.0),
+            PI / 8.0,
+            PI / 4.0,
+            Rgb::new(1.0, 1.0, 1.0),
+        )
+    }
+
+    #[test]
+    fn a_spotlight_normalizes_its_direction() {
+        assert_eq!(downlight().direction(), vec3(0.0, -1.0, 0.0));
+    }
+
+    #[test]
+    fn points_inside_the_inner_cone_are_fully_lit() {
+        let light = downlight();
+        assert_eq!(light.falloff(pt3(0.0, 0.0, 0.0)), 1.0);
+        assert_eq!(light.falloff(pt3(0.3, 0.0, 0.0)), 1.0);
+    }
+
+    #[test]
+    fn points_outside_the_outer_cone_are_unlit() {
+        let light = downlight();
+        assert_eq!(light.falloff(pt3(1.5, 0.0, 0.0)), 0.0);
+        assert_eq!(light.falloff(pt3(0.0, 2.0, 0.0)), 0.0);
+    }
+
+    #[test]
+    fn light_fades_smoothly_between_the_cones() {
+        let light = downlight();
+        let falloffs: Vec<f32> = [0.5, 0.7, 0.9]
+            .into_iter()
+            .map(|x| light.falloff(pt3(x, 0.0, 0.0)))
+            .collect();
+        assert!(falloffs[0] < 1.0 && falloffs[2] > 0.0, "{falloffs:?}");
+        assert!(falloffs.windows(2).all(|pair| pair[0] > pair[1]));
+    }
+
+    #[test]
+    fn the_point_at_the_light_itself_is_lit() {
+        assert_eq!(downlight().falloff(pt3(0.0, 1.0, 0.0)), 1.0);
+    }
+
+    #[test]
+    fn the_intensity_at_a_point_includes_the_falloff() {
+        let world = World::new();
+        let light = downlight();
+        assert_eq!(light.intensity_at(pt3(0.0, 0.0, 0.0), 0.0, &world), 1.0);
+        assert_eq!(light.intensity_at(pt3(1.5, 0.0, 0.0), 0.0, &world), 0.0);
+    }
+}
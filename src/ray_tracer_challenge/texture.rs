@@ -0,0 +1,261 @@
+use std::sync::Arc;
+
+use camino::Utf8Path;
+use image::ImageResult;
+
+use crate::library::canvas::Canvas;
+
+use super::Rgb;
+
+/// How a texture blends the pixels around a lookup.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TextureFilter {
+    /// The single closest pixel. Blocky when magnified.
+    Nearest,
+    /// A blend of the four closest pixels, weighted by distance.
+    #[default]
+    Bilinear,
+}
+
+/// What a texture does with lookups past its edges.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TextureWrap {
+    /// Tiles the image.
+    Repeat,
+    /// Stretches the edge pixels outwards.
+    #[default]
+    Clamp,
+}
+
+/// An image to look colors up in. Cheap to clone, so materials can share one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Texture {
+    width: u32,
+    height: u32,
+    rgbs: Arc<[Rgb]>,
+    pub filter: TextureFilter,
+    /// Across the image.
+    pub wrap_u: TextureWrap,
+    /// Up and down the image.
+    pub wrap_v: TextureWrap,
+}
+
+impl Texture {
+    pub fn from_canvas(canvas: &Canvas) -> Texture {
+        Texture::from_rgbs(
+            canvas.width() as u32,
+            canvas.height() as u32,
+            canvas.iter_pixels().map(|pixel| pixel.rgb).collect(),
+        )
+    }
+
+    /// A 1x1 texture, mostly useful for tests and placeholders.
+    pub fn solid(rgb: Rgb) -> Texture {
+        Texture::from_rgbs(1, 1, Arc::new([rgb]))
+    }
+
+    fn from_rgbs(width: u32, height: u32, rgbs: Arc<[Rgb]>) -> Texture {
+        Texture {
+            width,
+            height,
+            rgbs,
+            filter: TextureFilter::default(),
+            wrap_u: TextureWrap::default(),
+            wrap_v: TextureWrap::default(),
+        }
+    }
+
+    /// Loads any image the `image` crate supports. Components are scaled to
+    /// 0.0 to 1.0 and used as stored, just like `Canvas::load_image`, so a
+    /// texture lit by white light renders and saves as the same color it was
+    /// loaded as. Floating point formats like Radiance `.hdr` and OpenEXR keep
+    /// their full range.
+    pub fn load(path: &Utf8Path) -> ImageResult<Texture> {
+        let image = image::open(path)?.into_rgb32f();
+        let rgbs = image
+            .pixels()
+            .map(|pixel| {
+                let [red, green, blue] = pixel.0;
+                Rgb::new(red, green, blue)
+            })
+            .collect();
+        Ok(Texture::from_rgbs(image.width(), image.height(), rgbs))
+    }
+
+    pub fn with_filter(mut self, filter: TextureFilter) -> Texture {
+        self.filter = filter;
+        self
+    }
+
+    /// Wraps both across and up and down the image the same way.
+    pub fn with_wrap(mut self, wrap: TextureWrap) -> Texture {
+        self.wrap_u = wrap;
+        self.wrap_v = wrap;
+        self
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// The pixel at `(x, y)`, counting from the top left. Coordinates off the
+    /// edge are wrapped according to `wrap_u` and `wrap_v`.
+    pub fn texel(&self, x: i64, y: i64) -> Rgb {
+        if self.rgbs.is_empty() {
+            return Rgb::black();
+        }
+        let x = wrap(x, self.width, self.wrap_u);
+        let y = wrap(y, self.height, self.wrap_v);
+        self.rgbs[y * self.width as usize + x]
+    }
+
+    /// The color at `(u, v)`, where both run from 0.0 to 1.0 and `v` runs
+    /// from the bottom of the image to the top.
+    pub fn color_at_uv(&self, u: f32, v: f32) -> Rgb {
+        let x = u * self.width as f32;
+        let y = (1.0 - v) * self.height as f32;
+        match self.filter {
+            TextureFilter::Nearest => self.texel(x.floor() as i64, y.floor() as i64),
+            TextureFilter::Bilinear => {
+                // Pixel centers are half a pixel in from their corners.
+                let x = x - 0.5;
+                let y = y - 0.5;
+                let x0 = x.floor();
+                let y0 = y.floor();
+                let tx = x - x0;
+                let ty = y - y0;
+                let (x0, y0) = (x0 as i64, y0 as i64);
+                let top = self.texel(x0, y0) * (1.0 - tx) + self.texel(x0 + 1, y0) * tx;
+                let bottom = self.texel(x0, y0 + 1) * (1.0 - tx) + self.texel(x0 + 1, y0 + 1) * tx;
+                top * (1.0 - ty) + bottom * ty
+            }
+        }
+    }
+}
+
+fn wrap(coordinate: i64, size: u32, wrap: TextureWrap) -> usize {
+    match wrap {
+        TextureWrap::Repeat => coordinate.rem_euclid(size as i64) as usize,
+        TextureWrap::Clamp => coordinate.clamp(0, size as i64 - 1) as usize,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{
+        pt2, pt3, vec3, Camera, Material, Pattern, PointLight, Shape, Transform, UvMapping, World,
+    };
+    use super::*;
+    use camino::Utf8PathBuf;
+    use std::f32::consts::PI;
+
+    /// Red and green along the top, blue and white along the bottom.
+    fn two_by_two() -> Texture {
+        let mut canvas: Canvas = Canvas::new(2, 2);
+        canvas.set_pixel_rgb(pt2(0.0, 0.0), Rgb::new(1.0, 0.0, 0.0));
+        canvas.set_pixel_rgb(pt2(1.0, 0.0), Rgb::new(0.0, 1.0, 0.0));
+        canvas.set_pixel_rgb(pt2(0.0, 1.0), Rgb::new(0.0, 0.0, 1.0));
+        canvas.set_pixel_rgb(pt2(1.0, 1.0), Rgb::new(1.0, 1.0, 1.0));
+        Texture::from_canvas(&canvas)
+    }
+
+    fn temporary_png(name: &str, component: u8) -> Utf8PathBuf {
+        let path = std::env::temp_dir().join(name);
+        let image = image::RgbImage::from_pixel(1, 1, image::Rgb([component; 3]));
+        image.save(&path).unwrap();
+        Utf8PathBuf::from_path_buf(path).unwrap()
+    }
+
+    #[test]
+    fn looking_up_colors_by_uv() {
+        let texture = two_by_two();
+        let cases = [
+            (0.25, 0.75, Rgb::new(1.0, 0.0, 0.0)),
+            (0.75, 0.75, Rgb::new(0.0, 1.0, 0.0)),
+            (0.25, 0.25, Rgb::new(0.0, 0.0, 1.0)),
+            (0.75, 0.25, Rgb::new(1.0, 1.0, 1.0)),
+            // The edges clamp.
+            (1.0, 1.0, Rgb::new(0.0, 1.0, 0.0)),
+            (0.0, 0.0, Rgb::new(0.0, 0.0, 1.0)),
+        ];
+        for (u, v, expected) in cases {
+            assert_eq!(texture.color_at_uv(u, v), expected, "{u} {v}");
+        }
+    }
+
+    #[test]
+    fn nearest_filtering_picks_one_pixel() {
+        let texture = two_by_two().with_filter(TextureFilter::Nearest);
+        assert_eq!(texture.color_at_uv(0.45, 0.55), Rgb::new(1.0, 0.0, 0.0));
+        assert_eq!(texture.color_at_uv(0.55, 0.55), Rgb::new(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn bilinear_filtering_blends_neighboring_pixels() {
+        let texture = two_by_two();
+        assert_eq!(texture.color_at_uv(0.5, 0.75), Rgb::new(0.5, 0.5, 0.0));
+        assert_eq!(texture.color_at_uv(0.5, 0.5), Rgb::new(0.5, 0.5, 0.5));
+    }
+
+    #[test]
+    fn repeating_tiles_the_image() {
+        let texture = two_by_two().with_wrap(TextureWrap::Repeat);
+        assert_eq!(texture.color_at_uv(1.25, 0.75), Rgb::new(1.0, 0.0, 0.0));
+        assert_eq!(texture.color_at_uv(-0.25, -0.75), Rgb::new(1.0, 1.0, 1.0));
+        // Blends across the right edge into the left one.
+        assert_eq!(texture.color_at_uv(1.0, 0.75), Rgb::new(0.5, 0.5, 0.0));
+    }
+
+    #[test]
+    fn clamping_stretches_the_edges() {
+        let texture = two_by_two().with_wrap(TextureWrap::Clamp);
+        assert_eq!(texture.color_at_uv(1.25, 0.75), Rgb::new(0.0, 1.0, 0.0));
+        assert_eq!(texture.color_at_uv(-0.25, -0.75), Rgb::new(0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn loading_keeps_colors_as_stored() {
+        let path = temporary_png("texture_loading_keeps_colors.png", 128);
+        let loaded = Texture::load(&path).unwrap().texel(0, 0);
+        let canvas: Canvas = Canvas::load_image(&path).unwrap();
+        assert_eq!(Some(loaded), canvas.rgb_at(pt2(0.0, 0.0)));
+        assert!((loaded.red() - 128.0 / 255.0).abs() < 0.001, "{loaded:?}");
+    }
+
+    #[test]
+    fn a_texture_lit_by_white_light_saves_as_it_was_loaded() {
+        let path = temporary_png("texture_round_trip_input.png", 128);
+        let material = Material {
+            pattern: Some(Pattern::image(
+                Texture::load(&path).unwrap(),
+                UvMapping::Spherical,
+            )),
+            ambient: 1.0,
+            diffuse: 0.0,
+            specular: 0.0,
+            ..Material::default()
+        };
+        let mut world = World::new();
+        world.objects = vec![Shape::sphere().with_material(material)];
+        world.lights = vec![Box::new(PointLight::new(
+            pt3(-10.0, 10.0, -10.0),
+            Rgb::new(1.0, 1.0, 1.0),
+        ))];
+        let mut camera = Camera::new(5, 5, PI / 3.0);
+        let from = pt3(0.0, 0.0, -5.0);
+        let to = pt3(0.0, 0.0, 0.0);
+        camera.set_transform(Transform::view_transform(from, to, vec3(0.0, 1.0, 0.0)));
+        let canvas = camera.render(|ray| world.color_at(ray));
+
+        let output =
+            Utf8PathBuf::from_path_buf(std::env::temp_dir().join("texture_round_trip_output.png"))
+                .unwrap();
+        canvas.save_image(&output).unwrap();
+        let saved = image::open(&output).unwrap().into_rgb8();
+        assert_eq!(saved.get_pixel(2, 2).0, [128, 128, 128]);
+    }
+}
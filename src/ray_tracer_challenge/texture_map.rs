@@ -0,0 +1,279 @@
+use std::f32::consts::{PI, TAU};
+
+use super::{CubeFace, Point3, Rgb, Texture};
+
+/// Wraps a flat `(u, v)` space, where both run from 0.0 to 1.0, around a
+/// shape in its own object space.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UvMapping {
+    /// Around a unit sphere, like lines of longitude and latitude. `u` runs
+    /// once around the equator and `v` from the south pole to the north.
+    Spherical,
+    /// Tiled across the xz plane once per unit.
+    Planar,
+    /// Around the y axis once, and tiled up it once per unit.
+    Cylindrical,
+    /// Once across each face of the cube from -1.0 to 1.0. Use `CubeMap` to
+    /// give each face its own pattern.
+    Cube,
+}
+
+impl UvMapping {
+    pub fn uv(self, point: Point3) -> (f32, f32) {
+        match self {
+            UvMapping::Spherical => spherical_map(point),
+            UvMapping::Planar => planar_map(point),
+            UvMapping::Cylindrical => cylindrical_map(point),
+            UvMapping::Cube => CubeFace::from_point(point).uv(point),
+        }
+    }
+}
+
+pub fn spherical_map(point: Point3) -> (f32, f32) {
+    let (x, y, z) = (point.x(), point.y(), point.z());
+    let radius = (x * x + y * y + z * z).sqrt();
+    let phi = (y / radius).clamp(-1.0, 1.0).acos();
+    (longitude(point), 1.0 - phi / PI)
+}
+
+pub fn planar_map(point: Point3) -> (f32, f32) {
+    (point.x().rem_euclid(1.0), point.z().rem_euclid(1.0))
+}
+
+pub fn cylindrical_map(point: Point3) -> (f32, f32) {
+    (longitude(point), point.y().rem_euclid(1.0))
+}
+
+/// How far around the y axis `point` is, from 0.0 to 1.0, increasing
+/// counterclockwise when seen from above.
+fn longitude(point: Point3) -> f32 {
+    let theta = point.x().atan2(point.z());
+    1.0 - (theta / TAU + 0.5)
+}
+
+/// A pattern in flat `(u, v)` space, for wrapping around shapes with a
+/// `UvMapping`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum UvPattern {
+    /// `width` by `height` squares, alternating between `a` and `b`.
+    Checkers {
+        width: f32,
+        height: f32,
+        a: Rgb,
+        b: Rgb,
+    },
+    /// `main`, with a differently colored square in each corner. Handy for
+    /// checking which way up a mapping puts things.
+    AlignCheck {
+        main: Rgb,
+        upper_left: Rgb,
+        upper_right: Rgb,
+        bottom_left: Rgb,
+        bottom_right: Rgb,
+    },
+    Image(Texture),
+}
+
+impl UvPattern {
+    pub fn checkers(width: f32, height: f32, a: Rgb, b: Rgb) -> UvPattern {
+        UvPattern::Checkers {
+            width,
+            height,
+            a,
+            b,
+        }
+    }
+
+    pub fn color_at(&self, u: f32, v: f32) -> Rgb {
+        match self {
+            UvPattern::Checkers {
+                width,
+                height,
+                a,
+                b,
+            } => {
+                let u = (u * width).floor() as i64;
+                let v = (v * height).floor() as i64;
+                if (u + v).rem_euclid(2) == 0 {
+                    *a
+                } else {
+                    *b
+                }
+            }
+            UvPattern::AlignCheck {
+                main,
+                upper_left,
+                upper_right,
+                bottom_left,
+                bottom_right,
+            } => match (u, v) {
+                (u, v) if u < 0.2 && v > 0.8 => *upper_left,
+                (u, v) if u > 0.8 && v > 0.8 => *upper_right,
+                (u, v) if u < 0.2 && v < 0.2 => *bottom_left,
+                (u, v) if u > 0.8 && v < 0.2 => *bottom_right,
+                _ => *main,
+            },
+            UvPattern::Image(texture) => texture.color_at_uv(u, v),
+        }
+    }
+}
+
+/// A `UvPattern` wrapped around a shape with a `UvMapping`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextureMap {
+    pub uv_pattern: UvPattern,
+    pub mapping: UvMapping,
+}
+
+impl TextureMap {
+    pub fn new(uv_pattern: UvPattern, mapping: UvMapping) -> TextureMap {
+        TextureMap {
+            uv_pattern,
+            mapping,
+        }
+    }
+
+    pub fn color_at(&self, point: Point3) -> Rgb {
+        let (u, v) = self.mapping.uv(point);
+        self.uv_pattern.color_at(u, v)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::pt3;
+    use super::*;
+    use std::f32::consts::FRAC_1_SQRT_2;
+
+    fn black() -> Rgb {
+        Rgb::black()
+    }
+
+    fn white() -> Rgb {
+        Rgb::new(1.0, 1.0, 1.0)
+    }
+
+    fn assert_uv(actual: (f32, f32), expected: (f32, f32)) {
+        assert!(
+            (actual.0 - expected.0).abs() < 0.0001 && (actual.1 - expected.1).abs() < 0.0001,
+            "{actual:?} != {expected:?}"
+        );
+    }
+
+    #[test]
+    fn checker_pattern_in_2d() {
+        let checkers = UvPattern::checkers(2.0, 2.0, black(), white());
+        let cases = [
+            (0.0, 0.0, black()),
+            (0.5, 0.0, white()),
+            (0.0, 0.5, white()),
+            (0.5, 0.5, black()),
+            (1.0, 1.0, black()),
+        ];
+        for (u, v, expected) in cases {
+            assert_eq!(checkers.color_at(u, v), expected, "{u} {v}");
+        }
+    }
+
+    #[test]
+    fn using_a_spherical_mapping_on_a_3d_point() {
+        let cases = [
+            (pt3(0.0, 0.0, -1.0), (0.0, 0.5)),
+            (pt3(1.0, 0.0, 0.0), (0.25, 0.5)),
+            (pt3(0.0, 0.0, 1.0), (0.5, 0.5)),
+            (pt3(-1.0, 0.0, 0.0), (0.75, 0.5)),
+            (pt3(0.0, 1.0, 0.0), (0.5, 1.0)),
+            (pt3(0.0, -1.0, 0.0), (0.5, 0.0)),
+            (pt3(FRAC_1_SQRT_2, FRAC_1_SQRT_2, 0.0), (0.25, 0.75)),
+        ];
+        for (point, expected) in cases {
+            assert_uv(spherical_map(point), expected);
+        }
+    }
+
+    #[test]
+    fn using_a_texture_map_pattern_with_a_spherical_map() {
+        let checkers = UvPattern::checkers(16.0, 8.0, black(), white());
+        let texture_map = TextureMap::new(checkers, UvMapping::Spherical);
+        let cases = [
+            (pt3(0.4315, 0.4670, 0.7719), white()),
+            (pt3(-0.9654, 0.2552, -0.0534), black()),
+            (pt3(0.1039, 0.7090, 0.6975), white()),
+            (pt3(-0.4986, -0.7856, -0.3663), black()),
+            (pt3(-0.0317, -0.9395, 0.3411), black()),
+            (pt3(0.4809, -0.7721, 0.4154), black()),
+            (pt3(0.0285, -0.9612, -0.2745), black()),
+            (pt3(-0.5734, -0.2162, -0.7903), white()),
+            (pt3(0.7688, -0.1470, 0.6223), black()),
+            (pt3(-0.7652, 0.2175, 0.6060), black()),
+        ];
+        for (point, expected) in cases {
+            assert_eq!(texture_map.color_at(point), expected, "{point:?}");
+        }
+    }
+
+    #[test]
+    fn using_a_planar_mapping_on_a_3d_point() {
+        let cases = [
+            (pt3(0.25, 0.0, 0.5), (0.25, 0.5)),
+            (pt3(0.25, 0.0, -0.25), (0.25, 0.75)),
+            (pt3(0.25, 0.5, -0.25), (0.25, 0.75)),
+            (pt3(1.25, 0.0, 0.5), (0.25, 0.5)),
+            (pt3(0.25, 0.0, -1.75), (0.25, 0.25)),
+            (pt3(1.0, 0.0, -1.0), (0.0, 0.0)),
+            (pt3(0.0, 0.0, 0.0), (0.0, 0.0)),
+        ];
+        for (point, expected) in cases {
+            assert_uv(planar_map(point), expected);
+        }
+    }
+
+    #[test]
+    fn using_a_cylindrical_mapping_on_a_3d_point() {
+        let cases = [
+            (pt3(0.0, 0.0, -1.0), (0.0, 0.0)),
+            (pt3(0.0, 0.5, -1.0), (0.0, 0.5)),
+            (pt3(0.0, 1.0, -1.0), (0.0, 0.0)),
+            (pt3(FRAC_1_SQRT_2, 0.5, -FRAC_1_SQRT_2), (0.125, 0.5)),
+            (pt3(1.0, 0.5, 0.0), (0.25, 0.5)),
+            (pt3(FRAC_1_SQRT_2, 0.5, FRAC_1_SQRT_2), (0.375, 0.5)),
+            (pt3(0.0, -0.25, 1.0), (0.5, 0.75)),
+            (pt3(-FRAC_1_SQRT_2, 0.5, FRAC_1_SQRT_2), (0.625, 0.5)),
+            (pt3(-1.0, 1.25, 0.0), (0.75, 0.25)),
+            (pt3(-FRAC_1_SQRT_2, 0.5, -FRAC_1_SQRT_2), (0.875, 0.5)),
+        ];
+        for (point, expected) in cases {
+            assert_uv(cylindrical_map(point), expected);
+        }
+    }
+
+    #[test]
+    fn layout_of_the_align_check_pattern() {
+        let pattern = UvPattern::AlignCheck {
+            main: white(),
+            upper_left: Rgb::new(1.0, 0.0, 0.0),
+            upper_right: Rgb::new(1.0, 1.0, 0.0),
+            bottom_left: Rgb::new(0.0, 1.0, 0.0),
+            bottom_right: Rgb::new(0.0, 1.0, 1.0),
+        };
+        let cases = [
+            (0.5, 0.5, white()),
+            (0.1, 0.9, Rgb::new(1.0, 0.0, 0.0)),
+            (0.9, 0.9, Rgb::new(1.0, 1.0, 0.0)),
+            (0.1, 0.1, Rgb::new(0.0, 1.0, 0.0)),
+            (0.9, 0.1, Rgb::new(0.0, 1.0, 1.0)),
+        ];
+        for (u, v, expected) in cases {
+            assert_eq!(pattern.color_at(u, v), expected, "{u} {v}");
+        }
+    }
+
+    #[test]
+    fn a_cube_mapping_repeats_the_pattern_on_every_face() {
+        let checkers = UvPattern::checkers(2.0, 2.0, black(), white());
+        let texture_map = TextureMap::new(checkers, UvMapping::Cube);
+        assert_eq!(texture_map.color_at(pt3(-0.5, -0.5, 1.0)), black());
+        assert_eq!(texture_map.color_at(pt3(1.0, -0.5, 0.5)), black());
+        assert_eq!(texture_map.color_at(pt3(0.5, 1.0, 0.5)), white());
+    }
+}
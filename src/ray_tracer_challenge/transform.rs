@@ -0,0 +1,312 @@
+use std::ops::Mul;
+
+use crate::extensions::GlamVec4Extension;
+
+use super::{Point3, Vec3};
+
+/// A 4x4 transformation matrix.
+///
+/// Transformations are applied right to left when multiplied, so
+/// `translation * rotation * point` rotates first. `then` reads left to right
+/// instead: `rotation.then(translation)` also rotates first.
+#[derive(Debug, Clone, Copy)]
+pub struct Transform {
+    pub matrix: glam::Mat4,
+}
+
+impl Transform {
+    pub fn identity() -> Transform {
+        Transform {
+            matrix: glam::Mat4::IDENTITY,
+        }
+    }
+    pub fn translation(x: f32, y: f32, z: f32) -> Transform {
+        Transform {
+            matrix: glam::Mat4::from_translation(glam::Vec3::new(x, y, z)),
+        }
+    }
+    pub fn scaling(x: f32, y: f32, z: f32) -> Transform {
+        Transform {
+            matrix: glam::Mat4::from_scale(glam::Vec3::new(x, y, z)),
+        }
+    }
+    /// Rotates clockwise by `radians` when looking down the x axis towards the origin.
+    pub fn rotation_x(radians: f32) -> Transform {
+        Transform {
+            matrix: glam::Mat4::from_rotation_x(radians),
+        }
+    }
+    pub fn rotation_y(radians: f32) -> Transform {
+        Transform {
+            matrix: glam::Mat4::from_rotation_y(radians),
+        }
+    }
+    pub fn rotation_z(radians: f32) -> Transform {
+        Transform {
+            matrix: glam::Mat4::from_rotation_z(radians),
+        }
+    }
+    /// Moves each component in proportion to the other two. For example,
+    /// `x_y` is how much x moves in proportion to y.
+    pub fn shearing(x_y: f32, x_z: f32, y_x: f32, y_z: f32, z_x: f32, z_y: f32) -> Transform {
+        // glam matrices are column-major, so each row of the book's matrix
+        // becomes a column here.
+        let matrix = glam::Mat4::from_cols_array_2d(&[
+            [1.0, y_x, z_x, 0.0],
+            [x_y, 1.0, z_y, 0.0],
+            [x_z, y_z, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ]);
+        Transform { matrix }
+    }
+    /// Orients the world relative to an eye at `from`, looking at `to`, with
+    /// `up` roughly pointing up.
+    pub fn view_transform(from: Point3, to: Point3, up: Vec3) -> Transform {
+        let forward = (to - from).normalize();
+        let left = forward.cross(up.normalize());
+        let true_up = left.cross(forward);
+        let orientation = glam::Mat4::from_cols_array_2d(&[
+            [left.x(), true_up.x(), -forward.x(), 0.0],
+            [left.y(), true_up.y(), -forward.y(), 0.0],
+            [left.z(), true_up.z(), -forward.z(), 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ]);
+        let orientation = Transform {
+            matrix: orientation,
+        };
+        orientation * Transform::translation(-from.x(), -from.y(), -from.z())
+    }
+    pub fn inverse(&self) -> Transform {
+        Transform {
+            matrix: self.matrix.inverse(),
+        }
+    }
+    pub fn transpose(&self) -> Transform {
+        Transform {
+            matrix: self.matrix.transpose(),
+        }
+    }
+    /// Applies `self`, then `next`.
+    pub fn then(&self, next: Transform) -> Transform {
+        next * *self
+    }
+    /// Blends from `self` at `t = 0.0` to `other` at `t = 1.0`. Scale,
+    /// rotation and translation are interpolated separately, so rotations
+    /// sweep around instead of squashing through each other. Shearing can't
+    /// be separated out like that and is lost.
+    pub fn interpolate(&self, other: Transform, t: f32) -> Transform {
+        let (scale_a, rotation_a, translation_a) = self.matrix.to_scale_rotation_translation();
+        let (scale_b, rotation_b, translation_b) = other.matrix.to_scale_rotation_translation();
+        Transform {
+            matrix: glam::Mat4::from_scale_rotation_translation(
+                scale_a.lerp(scale_b, t),
+                rotation_a.slerp(rotation_b, t),
+                translation_a.lerp(translation_b, t),
+            ),
+        }
+    }
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Transform::identity()
+    }
+}
+
+impl PartialEq for Transform {
+    fn eq(&self, other: &Self) -> bool {
+        (0..4).all(|i| self.matrix.col(i).almost_eq(other.matrix.col(i)))
+    }
+}
+
+impl Mul<Transform> for Transform {
+    type Output = Transform;
+
+    fn mul(self, other: Transform) -> Transform {
+        Transform {
+            matrix: self.matrix * other.matrix,
+        }
+    }
+}
+
+impl Mul<Point3> for Transform {
+    type Output = Point3;
+
+    fn mul(self, point: Point3) -> Point3 {
+        Point3 {
+            vec: self.matrix * point.vec,
+        }
+    }
+}
+
+impl Mul<Vec3> for Transform {
+    type Output = Vec3;
+
+    fn mul(self, vector: Vec3) -> Vec3 {
+        Vec3 {
+            vec: self.matrix * vector.vec,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{pt3, vec3};
+    use super::*;
+    use std::f32::consts::{FRAC_1_SQRT_2, PI};
+
+    #[test]
+    fn multiplying_by_a_translation_matrix() {
+        let transform = Transform::translation(5.0, -3.0, 2.0);
+        let p = pt3(-3.0, 4.0, 5.0);
+        assert_eq!(transform * p, pt3(2.0, 1.0, 7.0));
+    }
+
+    #[test]
+    fn multiplying_by_the_inverse_of_a_translation_matrix() {
+        let transform = Transform::translation(5.0, -3.0, 2.0);
+        let p = pt3(-3.0, 4.0, 5.0);
+        assert_eq!(transform.inverse() * p, pt3(-8.0, 7.0, 3.0));
+    }
+
+    #[test]
+    fn translation_does_not_affect_vectors() {
+        let transform = Transform::translation(5.0, -3.0, 2.0);
+        let v = vec3(-3.0, 4.0, 5.0);
+        assert_eq!(transform * v, v);
+    }
+
+    #[test]
+    fn a_scaling_matrix_applied_to_a_point_and_a_vector() {
+        let transform = Transform::scaling(2.0, 3.0, 4.0);
+        assert_eq!(transform * pt3(-4.0, 6.0, 8.0), pt3(-8.0, 18.0, 32.0));
+        assert_eq!(transform * vec3(-4.0, 6.0, 8.0), vec3(-8.0, 18.0, 32.0));
+    }
+
+    #[test]
+    fn reflection_is_scaling_by_a_negative_value() {
+        let transform = Transform::scaling(-1.0, 1.0, 1.0);
+        assert_eq!(transform * pt3(2.0, 3.0, 4.0), pt3(-2.0, 3.0, 4.0));
+    }
+
+    #[test]
+    fn rotating_a_point_around_each_axis() {
+        let half_quarter = FRAC_1_SQRT_2;
+        assert_eq!(
+            Transform::rotation_x(PI / 4.0) * pt3(0.0, 1.0, 0.0),
+            pt3(0.0, half_quarter, half_quarter)
+        );
+        assert_eq!(
+            Transform::rotation_x(PI / 2.0) * pt3(0.0, 1.0, 0.0),
+            pt3(0.0, 0.0, 1.0)
+        );
+        assert_eq!(
+            Transform::rotation_y(PI / 4.0) * pt3(0.0, 0.0, 1.0),
+            pt3(half_quarter, 0.0, half_quarter)
+        );
+        assert_eq!(
+            Transform::rotation_z(PI / 4.0) * pt3(0.0, 1.0, 0.0),
+            pt3(-half_quarter, half_quarter, 0.0)
+        );
+    }
+
+    #[test]
+    fn shearing_moves_each_component_in_proportion_to_the_others() {
+        let p = pt3(2.0, 3.0, 4.0);
+        let cases = [
+            ((1.0, 0.0, 0.0, 0.0, 0.0, 0.0), pt3(5.0, 3.0, 4.0)),
+            ((0.0, 1.0, 0.0, 0.0, 0.0, 0.0), pt3(6.0, 3.0, 4.0)),
+            ((0.0, 0.0, 1.0, 0.0, 0.0, 0.0), pt3(2.0, 5.0, 4.0)),
+            ((0.0, 0.0, 0.0, 1.0, 0.0, 0.0), pt3(2.0, 7.0, 4.0)),
+            ((0.0, 0.0, 0.0, 0.0, 1.0, 0.0), pt3(2.0, 3.0, 6.0)),
+            ((0.0, 0.0, 0.0, 0.0, 0.0, 1.0), pt3(2.0, 3.0, 7.0)),
+        ];
+        for ((x_y, x_z, y_x, y_z, z_x, z_y), expected) in cases {
+            let transform = Transform::shearing(x_y, x_z, y_x, y_z, z_x, z_y);
+            assert_eq!(transform * p, expected);
+        }
+    }
+
+    #[test]
+    fn chained_transformations_are_applied_in_order() {
+        let p = pt3(1.0, 0.0, 1.0);
+        let transform = Transform::rotation_x(PI / 2.0)
+            .then(Transform::scaling(5.0, 5.0, 5.0))
+            .then(Transform::translation(10.0, 5.0, 7.0));
+        assert_eq!(transform * p, pt3(15.0, 0.0, 7.0));
+    }
+
+    #[test]
+    fn the_view_transformation_for_the_default_orientation() {
+        let from = pt3(0.0, 0.0, 0.0);
+        let to = pt3(0.0, 0.0, -1.0);
+        let up = vec3(0.0, 1.0, 0.0);
+        assert_eq!(
+            Transform::view_transform(from, to, up),
+            Transform::identity()
+        );
+    }
+
+    #[test]
+    fn a_view_transformation_looking_in_positive_z_direction() {
+        let from = pt3(0.0, 0.0, 0.0);
+        let to = pt3(0.0, 0.0, 1.0);
+        let up = vec3(0.0, 1.0, 0.0);
+        assert_eq!(
+            Transform::view_transform(from, to, up),
+            Transform::scaling(-1.0, 1.0, -1.0)
+        );
+    }
+
+    #[test]
+    fn the_view_transformation_moves_the_world() {
+        let from = pt3(0.0, 0.0, 8.0);
+        let to = pt3(0.0, 0.0, 0.0);
+        let up = vec3(0.0, 1.0, 0.0);
+        assert_eq!(
+            Transform::view_transform(from, to, up),
+            Transform::translation(0.0, 0.0, -8.0)
+        );
+    }
+
+    #[test]
+    fn an_arbitrary_view_transformation() {
+        let from = pt3(1.0, 3.0, 2.0);
+        let to = pt3(4.0, -2.0, 8.0);
+        let up = vec3(1.0, 1.0, 0.0);
+        let expected = glam::Mat4::from_cols_array_2d(&[
+            [-0.50709, 0.76772, -0.35857, 0.0],
+            [0.50709, 0.60609, 0.59761, 0.0],
+            [0.67612, 0.12122, -0.71714, 0.0],
+            [-2.36643, -2.82843, 0.0, 1.0],
+        ]);
+        let actual = Transform::view_transform(from, to, up);
+        for i in 0..4 {
+            let difference = actual.matrix.col(i) - expected.col(i);
+            assert!(difference.abs().max_element() < 0.0001);
+        }
+    }
+
+    #[test]
+    fn interpolating_between_two_transforms() {
+        let a = Transform::translation(0.0, 0.0, 0.0);
+        let b = Transform::translation(4.0, 0.0, -2.0);
+        assert_eq!(a.interpolate(b, 0.0), a);
+        assert_eq!(a.interpolate(b, 1.0), b);
+        assert_eq!(
+            a.interpolate(b, 0.5),
+            Transform::translation(2.0, 0.0, -1.0)
+        );
+    }
+
+    #[test]
+    fn interpolating_rotations_sweeps_around() {
+        let a = Transform::identity();
+        let b = Transform::rotation_z(PI / 2.0);
+        let halfway = a.interpolate(b, 0.5);
+        assert_eq!(halfway, Transform::rotation_z(PI / 4.0));
+        // Blending the matrices component-wise would shrink the point instead.
+        let point = halfway * pt3(1.0, 0.0, 0.0);
+        assert_eq!(point, pt3(FRAC_1_SQRT_2, FRAC_1_SQRT_2, 0.0));
+    }
+}
@@ -59,6 +59,22 @@ impl Vec3 {
 
         Vec3::new(cross.x, cross.y, cross.z)
     }
+    /// Reflects the vector around `normal`, like a ball bouncing off a wall.
+    pub fn reflect(&self, normal: Vec3) -> Vec3 {
+        *self - normal * 2.0 * self.dot(normal)
+    }
+    /// Two unit vectors perpendicular to this one and to each other. Which
+    /// two is arbitrary, but always the same for the same vector.
+    pub fn perpendiculars(&self) -> (Vec3, Vec3) {
+        let helper = if self.x().abs() > 0.9 {
+            vec3(0.0, 1.0, 0.0)
+        } else {
+            vec3(1.0, 0.0, 0.0)
+        };
+        let tangent = helper.cross(*self).normalize();
+        let bitangent = self.cross(tangent).normalize();
+        (tangent, bitangent)
+    }
 }
 
 impl PartialEq for Vec3 {
@@ -159,6 +175,22 @@ mod tests {
         assert_eq!(actual, expected);
     }
 
+    #[test]
+    fn perpendicular_vectors() {
+        for v in [
+            vec3(0.0, 1.0, 0.0),
+            vec3(1.0, 0.0, 0.0),
+            vec3(0.6, -0.8, 0.0),
+        ] {
+            let (tangent, bitangent) = v.perpendiculars();
+            assert!(tangent.dot(v).abs() < 1e-6);
+            assert!(bitangent.dot(v).abs() < 1e-6);
+            assert!(tangent.dot(bitangent).abs() < 1e-6);
+            assert!((tangent.length() - 1.0).abs() < 1e-6);
+            assert!((bitangent.length() - 1.0).abs() < 1e-6);
+        }
+    }
+
     #[test]
     fn negating_a_vector() {
         let v = vec3(1.0, -2.0, 3.0);
@@ -252,4 +284,22 @@ mod tests {
         let expected = vec3(1.0, -2.0, 1.0);
         assert_eq!(actual, expected);
     }
+
+    #[test]
+    fn reflecting_a_vector_approaching_at_45_degrees() {
+        let v = vec3(1.0, -1.0, 0.0);
+        let n = vec3(0.0, 1.0, 0.0);
+        assert_eq!(v.reflect(n), vec3(1.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn reflecting_a_vector_off_a_slanted_surface() {
+        let v = vec3(0.0, -1.0, 0.0);
+        let n = vec3(
+            std::f32::consts::FRAC_1_SQRT_2,
+            std::f32::consts::FRAC_1_SQRT_2,
+            0.0,
+        );
+        assert_eq!(v.reflect(n), vec3(1.0, 0.0, 0.0));
+    }
 }
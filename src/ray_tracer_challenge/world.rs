@@ -0,0 +1,356 @@
+use super::{
+    hit, lighting, Background, Computations, Intersection, Light, LightSample, Point3, Ray, Rgb,
+    Shape, ShapeLight,
+};
+
+#[derive(Debug, Default)]
+pub struct World {
+    pub objects: Vec<Shape>,
+    pub lights: Vec<Box<dyn Light>>,
+    /// What rays that miss every object see.
+    pub background: Background,
+}
+
+impl World {
+    pub fn new() -> World {
+        World::default()
+    }
+
+    /// Every intersection with every object, sorted by `t`.
+    pub fn intersect(&self, ray: Ray) -> Vec<Intersection<'_>> {
+        let mut intersections: Vec<Intersection> = self
+            .objects
+            .iter()
+            .flat_map(|object| object.intersect(ray))
+            .collect();
+        intersections.sort_by(|a, b| a.t.total_cmp(&b.t));
+        intersections
+    }
+
+    /// The sum of every light's contribution, each with its own shadow test,
+    /// plus any light the surface emits. Emissive shapes count as lights.
+    pub fn shade_hit(&self, comps: &Computations) -> Rgb {
+        let material = comps.object.material_at(comps.point, comps.time);
+        let mut total = material.emission;
+        // Shapes can't light themselves from the outside, so the shape being
+        // shaded is left out.
+        self.for_each_light(Some(comps.object), comps.time, |light| {
+            let light_intensity = light.intensity_at(comps.over_point, comps.time, self);
+            total = total
+                + lighting(
+                    &material,
+                    light,
+                    comps.over_point,
+                    comps.eyev,
+                    comps.normalv,
+                    light_intensity,
+                );
+        });
+        total
+    }
+
+    /// Calls `f` with everything in `lights`, then with a `ShapeLight` for
+    /// every emissive shape that can be sampled, apart from `excluded`. Moving
+    /// shapes light the world from where they are at `time`.
+    pub fn for_each_light(
+        &self,
+        excluded: Option<&Shape>,
+        time: f32,
+        mut f: impl FnMut(&dyn Light),
+    ) {
+        for light in &self.lights {
+            f(light.as_ref());
+        }
+        for object in &self.objects {
+            if excluded.is_some_and(|excluded| std::ptr::eq(object, excluded)) {
+                continue;
+            }
+            if let Some(light) = ShapeLight::new(object, time, ShapeLight::DEFAULT_NUM_SAMPLES) {
+                f(&light);
+            }
+        }
+    }
+
+    /// The color seen along `ray`. Rays that don't hit anything see the
+    /// background.
+    pub fn color_at(&self, ray: Ray) -> Rgb {
+        let intersections = self.intersect(ray);
+        match hit(&intersections) {
+            Some(hit) => self.shade_hit(&hit.prepare_computations(ray)),
+            None => self.background.color(ray.direction.normalize()),
+        }
+    }
+
+    /// Whether anything lies between `light_position` and `point`.
+    pub fn is_shadowed(&self, light_position: Point3, point: Point3) -> bool {
+        self.is_occluded(point, LightSample::toward(point, light_position), 0.0)
+    }
+
+    /// Whether anything lies between `point` and the light `sample` points
+    /// to, with moving shapes wherever they were at `time`.
+    pub fn is_occluded(&self, point: Point3, sample: LightSample, time: f32) -> bool {
+        let ray = Ray::new(point, sample.direction).with_time(time);
+        let intersections = self.intersect(ray);
+        hit(&intersections).is_some_and(|hit| hit.t < sample.distance)
+    }
+}
+
+/// The book's default world: a light above and to the left of two concentric
+/// spheres. Lots of tests are written against it.
+#[cfg(test)]
+pub fn default_world() -> World {
+    use super::{pt3, Material, PointLight, Transform};
+
+    let light = PointLight::new(pt3(-10.0, 10.0, -10.0), Rgb::new(1.0, 1.0, 1.0));
+    let outer = Shape::sphere().with_material(Material {
+        color: Rgb::new(0.8, 1.0, 0.6),
+        diffuse: 0.7,
+        specular: 0.2,
+        ..Material::default()
+    });
+    let inner = Shape::sphere().with_transform(Transform::scaling(0.5, 0.5, 0.5));
+    World {
+        objects: vec![outer, inner],
+        lights: vec![Box::new(light)],
+        background: Background::None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{pt3, vec3, Material, Motion, PointLight, Transform};
+    use super::*;
+
+    #[test]
+    fn creating_a_world() {
+        let world = World::new();
+        assert!(world.objects.is_empty());
+        assert!(world.lights.is_empty());
+    }
+
+    #[test]
+    fn intersect_a_world_with_a_ray() {
+        let world = default_world();
+        let ray = Ray::new(pt3(0.0, 0.0, -5.0), vec3(0.0, 0.0, 1.0));
+        let ts: Vec<f32> = world.intersect(ray).iter().map(|i| i.t).collect();
+        assert_eq!(ts, vec![4.0, 4.5, 5.5, 6.0]);
+    }
+
+    #[test]
+    fn shading_an_intersection() {
+        let world = default_world();
+        let ray = Ray::new(pt3(0.0, 0.0, -5.0), vec3(0.0, 0.0, 1.0));
+        let intersection = Intersection::new(4.0, &world.objects[0]);
+        let comps = intersection.prepare_computations(ray);
+        let rgb = world.shade_hit(&comps);
+        assert!((rgb - Rgb::new(0.38066, 0.47583, 0.2855))
+            .components()
+            .iter()
+            .all(|c| c.abs() < 0.0001));
+    }
+
+    #[test]
+    fn shading_an_intersection_from_the_inside() {
+        let mut world = default_world();
+        world.lights = vec![Box::new(PointLight::new(
+            pt3(0.0, 0.25, 0.0),
+            Rgb::new(1.0, 1.0, 1.0),
+        ))];
+        let ray = Ray::new(pt3(0.0, 0.0, 0.0), vec3(0.0, 0.0, 1.0));
+        let intersection = Intersection::new(0.5, &world.objects[1]);
+        let comps = intersection.prepare_computations(ray);
+        let rgb = world.shade_hit(&comps);
+        assert!((rgb - Rgb::new(0.90498, 0.90498, 0.90498))
+            .components()
+            .iter()
+            .all(|c| c.abs() < 0.001));
+    }
+
+    #[test]
+    fn the_color_when_a_ray_misses() {
+        let world = default_world();
+        let ray = Ray::new(pt3(0.0, 0.0, -5.0), vec3(0.0, 1.0, 0.0));
+        assert_eq!(world.color_at(ray), Rgb::black());
+    }
+
+    #[test]
+    fn the_color_when_a_ray_hits() {
+        let world = default_world();
+        let ray = Ray::new(pt3(0.0, 0.0, -5.0), vec3(0.0, 0.0, 1.0));
+        let rgb = world.color_at(ray);
+        assert!((rgb - Rgb::new(0.38066, 0.47583, 0.2855))
+            .components()
+            .iter()
+            .all(|c| c.abs() < 0.0001));
+    }
+
+    #[test]
+    fn the_color_with_an_intersection_behind_the_ray() {
+        let mut world = default_world();
+        world.objects[0].material.ambient = 1.0;
+        world.objects[1].material.ambient = 1.0;
+        let ray = Ray::new(pt3(0.0, 0.0, 0.75), vec3(0.0, 0.0, -1.0));
+        assert_eq!(world.color_at(ray), world.objects[1].material.color);
+    }
+
+    #[test]
+    fn is_shadowed_tests_for_occlusion_between_two_points() {
+        let world = default_world();
+        let light_position = pt3(-10.0, -10.0, -10.0);
+        let cases = [
+            (pt3(-10.0, -10.0, 10.0), false),
+            (pt3(10.0, 10.0, 10.0), true),
+            (pt3(-20.0, -20.0, -20.0), false),
+            (pt3(-5.0, -5.0, -5.0), false),
+        ];
+        for (point, expected) in cases {
+            assert_eq!(
+                world.is_shadowed(light_position, point),
+                expected,
+                "{point:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn shade_hit_is_given_an_intersection_in_shadow() {
+        let s1 = Shape::sphere();
+        let s2 = Shape::sphere().with_transform(Transform::translation(0.0, 0.0, 10.0));
+        let world = World {
+            objects: vec![s1, s2],
+            lights: vec![Box::new(PointLight::new(
+                pt3(0.0, 0.0, -10.0),
+                Rgb::new(1.0, 1.0, 1.0),
+            ))],
+            ..World::default()
+        };
+        let ray = Ray::new(pt3(0.0, 0.0, 5.0), vec3(0.0, 0.0, 1.0));
+        let intersection = Intersection::new(4.0, &world.objects[1]);
+        let comps = intersection.prepare_computations(ray);
+        assert_eq!(world.shade_hit(&comps), Rgb::new(0.1, 0.1, 0.1));
+    }
+
+    #[test]
+    fn shading_with_no_lights_is_black() {
+        let mut world = default_world();
+        world.lights.clear();
+        let ray = Ray::new(pt3(0.0, 0.0, -5.0), vec3(0.0, 0.0, 1.0));
+        assert_eq!(world.color_at(ray), Rgb::black());
+    }
+
+    #[test]
+    fn shading_sums_the_contribution_of_every_light() {
+        let mut world = default_world();
+        let ray = Ray::new(pt3(0.0, 0.0, -5.0), vec3(0.0, 0.0, 1.0));
+        let one_light = world.color_at(ray);
+        let light = PointLight::new(pt3(-10.0, 10.0, -10.0), Rgb::new(1.0, 1.0, 1.0));
+        world.lights.push(Box::new(light));
+        assert_eq!(world.color_at(ray), one_light + one_light);
+    }
+
+    #[test]
+    fn each_light_casts_its_own_shadows() {
+        let floor = Shape::plane();
+        let blocker = Shape::sphere().with_transform(Transform::translation(-5.0, 5.0, 0.0));
+        let mut world = World {
+            objects: vec![floor, blocker],
+            lights: vec![Box::new(PointLight::new(
+                pt3(-10.0, 10.0, 0.0),
+                Rgb::new(1.0, 1.0, 1.0),
+            ))],
+            ..World::default()
+        };
+        let ray = Ray::new(pt3(0.0, 1.0, 0.0), vec3(0.0, -1.0, 0.0));
+        let in_shadow = world.color_at(ray);
+
+        let light = PointLight::new(pt3(10.0, 10.0, 0.0), Rgb::new(1.0, 1.0, 1.0));
+        world.lights.push(Box::new(light));
+        let partly_lit = world.color_at(ray);
+
+        assert_eq!(in_shadow, Rgb::new(0.1, 0.1, 0.1));
+        assert!(partly_lit.red() > 0.2, "{partly_lit:?}");
+    }
+
+    #[test]
+    fn moving_shapes_cast_shadows_where_they_were_at_the_time() {
+        let motion = Motion::linear(
+            Transform::translation(0.0, 5.0, 0.0),
+            Transform::translation(10.0, 5.0, 0.0),
+        );
+        let world = World {
+            objects: vec![Shape::sphere().with_motion(motion)],
+            lights: vec![],
+            ..World::default()
+        };
+        let sample = LightSample::toward(pt3(0.0, 0.0, 0.0), pt3(0.0, 10.0, 0.0));
+        assert!(world.is_occluded(pt3(0.0, 0.0, 0.0), sample, 0.0));
+        assert!(!world.is_occluded(pt3(0.0, 0.0, 0.0), sample, 1.0));
+    }
+
+    #[test]
+    fn emissive_surfaces_glow_without_any_lights() {
+        let mut world = default_world();
+        world.lights.clear();
+        world.objects[0].material.emission = Rgb::new(0.5, 0.25, 0.0);
+        let ray = Ray::new(pt3(0.0, 0.0, -5.0), vec3(0.0, 0.0, 1.0));
+        assert_eq!(world.color_at(ray), Rgb::new(0.5, 0.25, 0.0));
+    }
+
+    #[test]
+    fn emissive_spheres_light_their_surroundings() {
+        let glow = Shape::sphere()
+            .with_transform(Transform::translation(0.0, 4.0, 0.0))
+            .with_material(Material {
+                emission: Rgb::new(16.0, 16.0, 16.0),
+                ..Material::default()
+            });
+        let floor = Shape::plane().with_material(Material {
+            ambient: 0.0,
+            diffuse: 1.0,
+            specular: 0.0,
+            ..Material::default()
+        });
+        let mut world = World {
+            objects: vec![floor, glow],
+            lights: vec![],
+            ..World::default()
+        };
+        let ray = Ray::new(pt3(0.0, 1.0, 0.0), vec3(0.0, -1.0, 0.0));
+        let lit = world.color_at(ray);
+        assert!((lit.red() - 1.0).abs() < 0.2, "{lit:?}");
+
+        world.objects[1].material.emission = Rgb::black();
+        assert_eq!(world.color_at(ray), Rgb::black());
+    }
+
+    #[test]
+    fn emissive_spheres_add_no_ambient_light() {
+        let glow = Shape::sphere()
+            .with_transform(Transform::translation(0.0, 4.0, 0.0))
+            .with_material(Material {
+                emission: Rgb::new(16.0, 16.0, 16.0),
+                ..Material::default()
+            });
+        let blocker = Shape::sphere().with_transform(Transform::translation(0.0, 2.0, 0.0));
+        let world = World {
+            objects: vec![Shape::plane(), blocker, glow],
+            lights: vec![],
+            ..World::default()
+        };
+        // The floor has the default ambient, but the glow is hidden behind
+        // the blocker, so none of its light reaches the floor.
+        let ray = Ray::new(pt3(0.0, 0.5, 0.0), vec3(0.0, -1.0, 0.0));
+        assert_eq!(world.color_at(ray), Rgb::black());
+    }
+
+    #[test]
+    fn rays_that_miss_see_the_background() {
+        let mut world = default_world();
+        world.background = Background::VerticalGradient {
+            bottom: Rgb::black(),
+            top: Rgb::new(1.0, 1.0, 1.0),
+        };
+        let ray = Ray::new(pt3(0.0, 0.0, -5.0), vec3(0.0, 2.0, 0.0));
+        assert_eq!(world.color_at(ray), Rgb::new(1.0, 1.0, 1.0));
+    }
+}
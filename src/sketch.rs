@@ -1,26 +1,84 @@
+use std::f32::consts::PI;
+
 use crate::library::canvas::Canvas;
+use crate::library::tile_renderer::{Tile, TileRenderer};
 // use crate::prelude::*;
-use crate::ray_tracer_challenge;
+use crate::ray_tracer_challenge::{pt3, sdf, vec3, Camera, Point2, Point3, Rgb, Transform};
 
 pub const CANVAS_WIDTH: f32 = 100.0;
 pub const CANVAS_HEIGHT: f32 = 100.0;
 
-pub struct Sketch {}
+pub struct Sketch {
+    tracer: sdf::SphereTracer,
+    stage: sdf::Stage,
+}
 
 impl Sketch {
     pub fn new() -> Self {
-        Sketch {}
+        Sketch {
+            tracer: sdf::SphereTracer::new(),
+            stage: sdf::Stage::studio(),
+        }
     }
 
     pub fn draw(&self, canvas: &mut Canvas) {
-        ray_tracer_challenge::simulate_projectiles(canvas);
-        // for xy in canvas.iter_points() {
-        //     let color = oklcha(0.5, 1.0, 0.5, 1.0);
-        //     canvas.set_pixel(xy, color);
-        // }
+        let camera = self.camera(canvas.width() as u32, canvas.height() as u32);
+        TileRenderer::new().render(canvas, |xy| self.shade(&camera, xy));
+    }
+
+    /// Called on a background thread by the window, which shows each tile
+    /// passed to `on_tile` as soon as it arrives. The image is refined in
+    /// passes, so a blocky preview shows up almost immediately.
+    pub fn render(&self, width: u32, height: u32, on_tile: &mut dyn FnMut(Tile)) {
+        let camera = self.camera(width, height);
+        TileRenderer::new().render_progressively(
+            width,
+            height,
+            |xy| self.shade(&camera, xy),
+            on_tile,
+        );
+    }
+
+    fn shade(&self, camera: &Camera, xy: Point2) -> Rgb {
+        camera.render_pixel(xy.x() as u32, xy.y() as u32, |ray| {
+            self.stage.color_at(&self.tracer, scene, ray)
+        })
+    }
+
+    fn camera(&self, width: u32, height: u32) -> Camera {
+        let mut camera = Camera::new(width, height, PI / 3.0);
+        let from = pt3(0.0, 1.5, -5.0);
+        let to = pt3(0.0, 0.0, 0.0);
+        camera.set_transform(Transform::view_transform(from, to, vec3(0.0, 1.0, 0.0)));
+        camera
     }
 }
 
-// pub fn scene(xyz: Vec3) -> f32 {
-//     sdf::sphere(xyz)
-// }
+/// The signed distance field the sketch renders with `sdf::SphereTracer`.
+pub fn scene(point: Point3) -> f32 {
+    sdf::sphere(point, 1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rendering_progressively_ends_with_the_drawn_image() {
+        let sketch = Sketch::new();
+        let mut expected: Canvas = Canvas::new(20, 15);
+        sketch.draw(&mut expected);
+
+        let mut num_tiles = 0;
+        let mut canvas: Canvas = Canvas::new(20, 15);
+        sketch.render(20, 15, &mut |tile| {
+            canvas.set_tile(&tile);
+            num_tiles += 1;
+        });
+
+        assert!(num_tiles > 1);
+        for xy in expected.iter_points() {
+            assert_eq!(canvas.rgb_at(xy), expected.rgb_at(xy));
+        }
+    }
+}